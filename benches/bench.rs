@@ -1,14 +1,33 @@
 use audio_graph::{Audiograph, Node, Watcher};
 use criterion::{criterion_group, criterion_main, Criterion};
 
-use audio_graph::{Mixer, SineWave};
+use audio_graph::{Convolver, Mixer, SineWave};
 
 const NUM_SAMPLES: usize = 64;
+const NUM_SAMPLES_WIDE: usize = 1024;
 
 fn create_empty_buffer<const N: usize>() -> Box<[f32; N]> {
     let buf = vec![0.0; N].into_boxed_slice();
     unsafe { Box::from_raw(Box::into_raw(buf) as *mut [f32; N]) }
 }
+
+// A mixer of `width` buses, each one mixing `width` sinewaves
+fn wide_graph<const N: usize>(width: usize) -> Watcher<f32, N> {
+    let mut mixer = Node::new("mixer", Mixer);
+    for i in 0..width {
+        let name: &'static str = Box::leak(format!("bus{}", i).into_boxed_str());
+        let mut bus = Node::new(name, Mixer);
+        for j in 0..width {
+            let name: &'static str = Box::leak(format!("sw{}_{}", i, j).into_boxed_str());
+            let freq = 100.0 * (1 + i * width + j) as f32;
+            bus.add_input(Node::new(name, SineWave::new(0.01, freq)));
+        }
+        mixer.add_input(bus);
+    }
+
+    Watcher::on(mixer)
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let sw1 = Node::new("sw1", SineWave::new(0.1, 2500.0));
     let sw2 = Node::new("sw2", SineWave::new(0.02, 9534.0));
@@ -28,6 +47,28 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("mixer_parallel_rtrb", |b| {
         b.iter(|| audio.stream_into_rtrb(true))
     });
+
+    // A sinewave convolved by a decaying impulse response of a second
+    let ir = (0..44100)
+        .map(|idx| (idx as f32 * 0.37).sin() * (-(idx as f32) / 8000.0).exp())
+        .collect();
+    let mut convolver = Node::new("convolver", Convolver::new(ir));
+    convolver.add_input(Node::new("sw", SineWave::new(0.1, 440.0)));
+    let mut audio = Audiograph::new(44100.0, Watcher::on(convolver));
+
+    c.bench_function("convolver_1s_ir", |b| {
+        b.iter(|| audio.stream_into(&mut buf, false))
+    });
+
+    let mut buf = create_empty_buffer::<NUM_SAMPLES_WIDE>();
+    let mut audio = Audiograph::new(44100.0, wide_graph(16));
+
+    c.bench_function("wide_parallelism", |b| {
+        b.iter(|| audio.stream_into(&mut buf, true))
+    });
+    c.bench_function("wide_sequential", |b| {
+        b.iter(|| audio.stream_into(&mut buf, false))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,63 @@
+use crate::sampling::SampleIdx;
+
+/// What a node knows about the sample it is processing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Context {
+    /// The sample rate of the graph, as number of samples per second
+    pub sample_rate: f32,
+    /// The absolute index of the sample being processed
+    pub sample: SampleIdx,
+    /// The number of samples of the block being streamed
+    pub block_size: usize,
+    pub transport: Transport,
+}
+
+/// The musical position of the graph
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transport {
+    /// Tempo in beats per minute
+    pub tempo: f32,
+    /// Position of the sample being processed, in beats since the start of the graph
+    pub beat: f64,
+}
+
+impl Context {
+    /// Number of samples in a beat at the current tempo
+    pub fn samples_per_beat(&self) -> f32 {
+        self.sample_rate * 60.0 / self.transport.tempo
+    }
+
+    // The context of the sample `num_samples` samples after the current one
+    pub(crate) fn offset(&self, num_samples: usize) -> Self {
+        let beats = num_samples as f64 / self.samples_per_beat() as f64;
+
+        Self {
+            sample: SampleIdx(self.sample.0 + num_samples),
+            transport: Transport {
+                beat: self.transport.beat + beats,
+                ..self.transport
+            },
+            ..*self
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100.0,
+            sample: SampleIdx(0),
+            block_size: 1,
+            transport: Transport::default(),
+        }
+    }
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            tempo: 120.0,
+            beat: 0.0,
+        }
+    }
+}
@@ -3,7 +3,6 @@ use crate::sampling::SampleIdx;
 use crate::Node;
 
 use crate::node::NodeTrait;
-use std::sync::{Arc, Mutex};
 pub enum Event<S, F, const N: usize>
 where
     S: rodio::Sample + Send + Sync + 'static,
@@ -19,7 +18,15 @@ where
     AddInput {
         sample: SampleIdx,
         name: &'static str,
-        input: Arc<Mutex<dyn NodeTrait<S, N>>>,
+        input: Box<dyn NodeTrait<S, N>>,
+    },
+    // Move a parameter from its value at `sample` to `target` in `num_samples` samples
+    Ramp {
+        sample: SampleIdx,
+        param: fn(&mut F) -> &mut f32,
+        target: f32,
+        num_samples: usize,
+        curve: RampCurve,
     },
     NoteOff {
         sample: SampleIdx,
@@ -27,9 +34,21 @@ where
     NoteOn {
         sample: SampleIdx,
     },
+    RemoveInput {
+        sample: SampleIdx,
+        name: &'static str,
+    },
+    DeleteNode {
+        sample: SampleIdx,
+    },
+    Reconnect {
+        sample: SampleIdx,
+        from: &'static str,
+        to: &'static str,
+    },
 }
 
-use crate::Audiograph;
+use crate::sampling::Timeline;
 impl<S, F, const N: usize> Event<S, F, N>
 where
     S: rodio::Sample + Send + Sync + 'static,
@@ -38,9 +57,9 @@ where
     pub fn update_params(
         fu: fn(&mut F) -> (),
         time: std::time::Duration,
-        audio: &Audiograph<S, N>,
+        audio: &impl Timeline<S, N>,
     ) -> Self {
-        let idx_sample = audio.get_sampling_rate().from_time(time);
+        let idx_sample = audio.sample_idx_at(time);
 
         Event::UpdateParams {
             sample: idx_sample,
@@ -50,60 +69,220 @@ where
         }
     }
 
-    pub fn note_on(time: std::time::Duration, audio: &Audiograph<S, N>) -> Self {
-        let idx_sample = audio.get_sampling_rate().from_time(time);
+    /// Move a parameter of the node from its current value to `target`, one sample at a time,
+    /// like the automation of the parameters of Web Audio. A ramp replaces the ramp
+    /// of the same parameter being played, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `param` - Gives the parameter of the process to move
+    /// * `target` - The value of the parameter at the end of the ramp
+    /// * `start` - When the ramp starts, the parameter keeps its value at this time
+    /// * `duration` - The time to reach `target`
+    /// * `curve` - The shape of the ramp
+    /// * `audio` - The timeline of the graph of the node
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use audio_graph::{Audiograph, Event, Node, RampCurve, SineWave, Watcher};
+    /// use std::time::Duration;
+    ///
+    /// let sine = Node::new("sine", SineWave::new(0.5, 440.0));
+    /// let mut audio = Audiograph::<f32, 64>::new(44100.0, Watcher::on(sine));
+    ///
+    /// // Fade out in a second
+    /// let fade = Event::ramp_to(
+    ///     |f: &mut SineWave| &mut f.params.ampl,
+    ///     0.0,
+    ///     Duration::from_secs(1),
+    ///     Duration::from_secs(1),
+    ///     RampCurve::Linear,
+    ///     &audio,
+    /// );
+    /// assert!(audio.register_event("sine", fade));
+    /// ```
+    pub fn ramp_to(
+        param: fn(&mut F) -> &mut f32,
+        target: f32,
+        start: std::time::Duration,
+        duration: std::time::Duration,
+        curve: RampCurve,
+        audio: &impl Timeline<S, N>,
+    ) -> Self {
+        let idx_sample = audio.sample_idx_at(start);
+        let idx_end = audio.sample_idx_at(start + duration);
+
+        Event::Ramp {
+            sample: idx_sample,
+            param,
+            target,
+            num_samples: idx_end.0.saturating_sub(idx_sample.0),
+            curve,
+        }
+    }
+
+    pub fn note_on(time: std::time::Duration, audio: &impl Timeline<S, N>) -> Self {
+        let idx_sample = audio.sample_idx_at(time);
 
         Event::NoteOn { sample: idx_sample }
     }
 
-    pub fn note_off(time: std::time::Duration, audio: &Audiograph<S, N>) -> Self {
-        let idx_sample = audio.get_sampling_rate().from_time(time);
+    pub fn note_off(time: std::time::Duration, audio: &impl Timeline<S, N>) -> Self {
+        let idx_sample = audio.sample_idx_at(time);
 
         Event::NoteOff { sample: idx_sample }
     }
 
     pub fn add_input<F2>(
-        node: Node<S, F2, N>,
+        mut node: Node<S, F2, N>,
         time: std::time::Duration,
-        audio: &Audiograph<S, N>,
+        audio: &impl Timeline<S, N>,
     ) -> Self
     where
         F2: Process<S> + Clone + 'static,
     {
-        let idx_sample = audio.get_sampling_rate().from_time(time);
+        let idx_sample = audio.sample_idx_at(time);
+        // The node is prepared now so that it is not while the graph is rendered
+        node.prepare(audio.sample_rate(), N);
 
         Event::AddInput {
             sample: idx_sample,
-            name: &node.name,
-            input: Arc::new(Mutex::new(node)),
+            name: node.name,
+            input: Box::new(node),
+        }
+    }
+
+    /// Remove the input `name` of the node, with all its own inputs
+    pub fn remove_input(
+        name: &'static str,
+        time: std::time::Duration,
+        audio: &impl Timeline<S, N>,
+    ) -> Self {
+        let idx_sample = audio.sample_idx_at(time);
+
+        Event::RemoveInput {
+            sample: idx_sample,
+            name,
+        }
+    }
+
+    /// Delete the node from the graph, with all its inputs
+    pub fn delete_node(time: std::time::Duration, audio: &impl Timeline<S, N>) -> Self {
+        let idx_sample = audio.sample_idx_at(time);
+
+        Event::DeleteNode { sample: idx_sample }
+    }
+
+    /// Move the node, with all its inputs, from being an input of `from` to being an input of `to`
+    pub fn reconnect(
+        from: &'static str,
+        to: &'static str,
+        time: std::time::Duration,
+        audio: &impl Timeline<S, N>,
+    ) -> Self {
+        let idx_sample = audio.sample_idx_at(time);
+
+        Event::Reconnect {
+            sample: idx_sample,
+            from,
+            to,
         }
     }
 
     pub fn play_on(self, node: &mut Node<S, F, N>) {
         match self {
             Event::UpdateParams { fu, .. } => (fu)(&mut node.f),
-            Event::NoteOn { .. } => node.on = true,
-            Event::NoteOff { .. } => node.on = false,
-            _ => ()
-            /*Event::AddInput { input, name, .. } => {
-                node.add_input_trait_object(name, input);
-            }*/
+            Event::Ramp {
+                sample,
+                param,
+                target,
+                num_samples,
+                curve,
+            } => {
+                let from = *(param)(&mut node.f);
+                node.start_ramp(Ramp {
+                    sample,
+                    param,
+                    from,
+                    target,
+                    num_samples,
+                    curve,
+                });
+            }
+            // The gate is routed to the process, the node is only muted if it does not handle it
+            Event::NoteOn { .. } => {
+                if !node.f.gate(true) {
+                    node.on = true;
+                }
+            }
+            Event::NoteOff { .. } => {
+                if !node.f.gate(false) {
+                    node.on = false;
+                }
+            }
+            // Events changing the topology are moved to the graph
+            // when the node enters it and are played by the graph
+            Event::AddInput { .. }
+            | Event::RemoveInput { .. }
+            | Event::DeleteNode { .. }
+            | Event::Reconnect { .. } => (),
+        }
+    }
+
+    // The node carried by the event, if any
+    pub(crate) fn into_node(self) -> Option<Box<dyn NodeTrait<S, N>>> {
+        match self {
+            Event::AddInput { input, .. } => Some(input),
+            _ => None,
+        }
+    }
+
+    // Convert an event modifying the graph topology into a graph event
+    // targeting the node `target`. Other events are given back.
+    pub(crate) fn into_graph_event(self, target: &'static str) -> Result<GraphEvent<S, N>, Self> {
+        match self {
+            Event::AddInput { sample, input, .. } => Ok(GraphEvent::AddInput {
+                sample,
+                to: target,
+                input,
+            }),
+            Event::RemoveInput { sample, name } => Ok(GraphEvent::RemoveInput {
+                sample,
+                from: target,
+                name,
+            }),
+            Event::DeleteNode { sample } => Ok(GraphEvent::DeleteNode {
+                sample,
+                name: target,
+            }),
+            Event::Reconnect { sample, from, to } => Ok(GraphEvent::Reconnect {
+                sample,
+                name: target,
+                from,
+                to,
+            }),
+            _ => Err(self),
         }
     }
 
     pub(crate) fn get_sample_idx(&self) -> SampleIdx {
         match self {
             Event::UpdateParams { sample, .. } => *sample,
+            Event::Ramp { sample, .. } => *sample,
             Event::NoteOff { sample } => *sample,
             Event::NoteOn { sample } => *sample,
             Event::AddInput { sample, .. } => *sample,
+            Event::RemoveInput { sample, .. } => *sample,
+            Event::DeleteNode { sample } => *sample,
+            Event::Reconnect { sample, .. } => *sample,
         }
     }
 }
 
 impl<S, F, const N: usize> PartialEq for Event<S, F, N>
 where
-S: rodio::Sample + Send + Sync + 'static,
+    S: rodio::Sample + Send + Sync + 'static,
     F: Process<S> + Clone + 'static,
 {
     fn eq(&self, other: &Self) -> bool {
@@ -140,3 +319,90 @@ where
         other.get_sample_idx().cmp(&self.get_sample_idx())
     }
 }
+
+/// Shape of the ramp of a parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RampCurve {
+    Linear,
+    /// The value is multiplied by the same ratio at each sample, as perceived pitches
+    /// and loudnesses. The ramp is linear if the values at its ends are not both
+    /// positive or both negative
+    Exponential,
+    /// Starts and ends smoothly, following a smoothstep
+    SCurve,
+}
+
+// A ramp being played on a parameter of a node
+pub(crate) struct Ramp<F> {
+    // The sample at which the parameter has the value `from`
+    pub(crate) sample: SampleIdx,
+    pub(crate) param: fn(&mut F) -> &mut f32,
+    pub(crate) from: f32,
+    pub(crate) target: f32,
+    pub(crate) num_samples: usize,
+    pub(crate) curve: RampCurve,
+}
+
+impl<F> Ramp<F> {
+    // The value of the parameter at the sample `sample`, and whether the ramp is over
+    pub(crate) fn value_at(&self, sample: SampleIdx) -> (f32, bool) {
+        let elapsed = sample.0.saturating_sub(self.sample.0);
+        if elapsed >= self.num_samples {
+            return (self.target, true);
+        }
+
+        let t = elapsed as f64 / self.num_samples as f64;
+        let (from, target) = (self.from as f64, self.target as f64);
+        let value = match self.curve {
+            RampCurve::Exponential if from * target > 0.0 => from * (target / from).powf(t),
+            RampCurve::Linear | RampCurve::Exponential => from + (target - from) * t,
+            RampCurve::SCurve => from + (target - from) * t * t * (3.0 - 2.0 * t),
+        };
+
+        (value as f32, false)
+    }
+}
+
+// Events changing the topology of the graph.
+//
+// They are played by the graph between two streamed samples, and not by the nodes,
+// so that the render plan and the node names lookup stay consistent.
+pub enum GraphEvent<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    AddInput {
+        sample: SampleIdx,
+        to: &'static str,
+        input: Box<dyn NodeTrait<S, N>>,
+    },
+    RemoveInput {
+        sample: SampleIdx,
+        from: &'static str,
+        name: &'static str,
+    },
+    DeleteNode {
+        sample: SampleIdx,
+        name: &'static str,
+    },
+    Reconnect {
+        sample: SampleIdx,
+        name: &'static str,
+        from: &'static str,
+        to: &'static str,
+    },
+}
+
+impl<S, const N: usize> GraphEvent<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    pub(crate) fn get_sample_idx(&self) -> SampleIdx {
+        match self {
+            GraphEvent::AddInput { sample, .. } => *sample,
+            GraphEvent::RemoveInput { sample, .. } => *sample,
+            GraphEvent::DeleteNode { sample, .. } => *sample,
+            GraphEvent::Reconnect { sample, .. } => *sample,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Radix-2 Fast Fourier Transform
+//
+// The twiddle factors and the bit reversal permutation are computed once when
+// the transform is planned so that transforming a buffer does not allocate.
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Complex {
+    pub(crate) re: f32,
+    pub(crate) im: f32,
+}
+
+impl Complex {
+    pub(crate) fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    pub(crate) fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Self;
+
+    fn mul(self, other: f32) -> Self {
+        Self::new(self.re * other, self.im * other)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Fft {
+    len: usize,
+    // exp(-2i * pi * k / len) for k in 0..len/2
+    twiddles: Vec<Complex>,
+    // Index of each element after the bit reversal permutation
+    reversed: Vec<usize>,
+}
+
+impl Fft {
+    // Plan the transforms of buffers of `len` elements, `len` being a power of two
+    pub(crate) fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two());
+
+        let twiddles = (0..len / 2)
+            .map(|k| {
+                let angle = -std::f64::consts::TAU * k as f64 / len as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+
+        let bits = len.trailing_zeros();
+        let reversed = (0..len)
+            .map(|idx| {
+                idx.reverse_bits()
+                    .checked_shr(usize::BITS - bits)
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        Self {
+            len,
+            twiddles,
+            reversed,
+        }
+    }
+
+    // Replace the samples of `buf` by their spectrum
+    pub(crate) fn forward(&self, buf: &mut [Complex]) {
+        self.transform(buf, false);
+    }
+
+    // Replace the spectrum in `buf` by its samples
+    pub(crate) fn inverse(&self, buf: &mut [Complex]) {
+        self.transform(buf, true);
+
+        let scale = 1.0 / self.len as f32;
+        for value in buf.iter_mut() {
+            *value = *value * scale;
+        }
+    }
+
+    fn transform(&self, buf: &mut [Complex], inverse: bool) {
+        assert_eq!(buf.len(), self.len);
+
+        for (idx, reversed) in self.reversed.iter().enumerate() {
+            if idx < *reversed {
+                buf.swap(idx, *reversed);
+            }
+        }
+
+        // Butterflies of the sub-transforms of `size` elements
+        let mut size = 2;
+        while size <= self.len {
+            let stride = self.len / size;
+            for start in (0..self.len).step_by(size) {
+                for k in 0..size / 2 {
+                    let twiddle = self.twiddles[k * stride];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+
+                    let even = buf[start + k];
+                    let odd = buf[start + k + size / 2] * twiddle;
+                    buf[start + k] = even + odd;
+                    buf[start + k + size / 2] = even - odd;
+                }
+            }
+            size *= 2;
+        }
+    }
+}
@@ -1,25 +1,46 @@
 use crate::node::{Node, NodeTrait, Process};
 
-use std::collections::HashMap;
+use crate::plan::RenderPlan;
+use crate::render_thread::RenderThread;
 
-use crate::node::Nodes;
+use crate::handle::{AudiographHandle, Command, Trash};
+use rtrb::{Consumer, Producer, RingBuffer};
 pub struct Audiograph<S, const N: usize>
 where
     S: rodio::Sample + Send + Sync + 'static,
 {
-    root: Watcher<S, N>,
     sample_rate: SamplingRate,
-    nodes: Nodes<S, N>,
-
-    pool: rayon::ThreadPool,
+    // The nodes compiled into a render plan
+    plan: RenderPlan<S, N>,
+    // Transport position: absolute index of the next sample to be streamed
+    clock: SampleIdx,
+    // Tempo and musical position of the next sample to be streamed
+    transport: Transport,
+    // Events changing the topology of the graph, sorted by decreasing sample indices
+    events: Vec<GraphEvent<S, N>>,
+    // Events carried by the nodes being added, before they are scheduled
+    added_events: Vec<GraphEvent<S, N>>,
+
+    // Thread pool processing the independent nodes in parallel.
+    // None when the graph is rendered in the calling thread only
+    pool: Option<rayon::ThreadPool>,
+
+    // Commands sent by the control handle, applied at the start of each block
+    commands: Option<Consumer<Box<dyn Command<S, N>>>>,
+    // Removed nodes and applied commands sent back to the handle to be deallocated
+    trash: Option<Producer<Trash<S, N>>>,
 }
 
 const NUM_WORKERS: usize = 4;
+// Number of graph events that can be scheduled before the queue has to grow,
+// which would allocate on the thread rendering the graph
+const EVENTS_CAPACITY: usize = 64;
 
-use crate::sampling::SamplingRate;
+use crate::context::{Context, Transport};
+use crate::event::GraphEvent;
+use crate::sampling::{SampleIdx, SamplingRate, Timeline};
 use crate::Event;
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use std::ops::Range;
 impl<S, const N: usize> Audiograph<S, N>
 where
     S: rodio::Sample + Send + Sync + 'static,
@@ -43,22 +64,63 @@ where
     /// audio.stream_into(&mut buf, true);
     /// ```
     pub fn new<T: Into<SamplingRate>>(sample_rate: T, root: Watcher<S, N>) -> Self {
+        Self::with_workers(sample_rate, root, NUM_WORKERS)
+    }
+
+    /// Crate a new audio graph rendered by a given number of worker threads
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate given as number of samples per second
+    /// * `root` - The root node of the graph
+    /// * `num_workers` - The number of threads of the pool processing the nodes in parallel.
+    ///   If 0, no thread is created and the graph is always rendered in the calling thread
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use audio_graph::{Watcher, Audiograph, Node};
+    /// use audio_graph::SineWave;
+    /// let sw1 = Node::new("sinewave", SineWave::new(0.1, 2500.0));
+    /// let mut audio = Audiograph::with_workers(44100.0, Watcher::on(sw1), 0);
+    /// let mut buf = Box::new([0.0; 1000]);
+    /// audio.stream_into(&mut buf, true);
+    /// ```
+    pub fn with_workers<T: Into<SamplingRate>>(
+        sample_rate: T,
+        root: Watcher<S, N>,
+        num_workers: usize,
+    ) -> Self {
         let sample_rate = sample_rate.into();
 
-        let mut nodes = HashMap::new();
-        root.collect_nodes(&mut nodes);
+        let mut events = vec![];
+        let plan = RenderPlan::new(Box::new(root.root), sample_rate.hz(), &mut events);
 
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(NUM_WORKERS)
-            .build()
-            .unwrap();
+        let pool = if num_workers > 0 {
+            Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_workers)
+                    .build()
+                    .unwrap(),
+            )
+        } else {
+            None
+        };
 
-        Self {
+        let mut audio = Self {
             sample_rate,
-            root,
-            nodes,
+            plan,
+            clock: SampleIdx(0),
+            transport: Transport::default(),
+            events: Vec::with_capacity(EVENTS_CAPACITY),
+            added_events: Vec::with_capacity(EVENTS_CAPACITY),
             pool,
-        }
+            commands: None,
+            trash: None,
+        };
+        audio.schedule_all(&mut events);
+
+        audio
     }
 
     /// Give a another set of nodes to the graph
@@ -67,43 +129,69 @@ where
     ///
     /// * `root` - New graph root
     pub fn set_root(&mut self, root: Watcher<S, N>) {
-        let mut nodes = HashMap::new();
-        root.collect_nodes(&mut nodes);
+        // The scheduled graph events were targeting the previous nodes
+        self.events.clear();
 
-        self.root = root;
-        self.nodes = nodes;
+        let mut events = vec![];
+        self.plan = RenderPlan::new(Box::new(root.root), self.sample_rate.hz(), &mut events);
+        self.schedule_all(&mut events);
     }
 
-    /// Give a another set of nodes to the graph
+    /// Add a node as an input of another node
     ///
     /// # Arguments
     ///
-    /// * `root` - New graph root
+    /// * `name` - The name of the node receiving the new input
+    /// * `input` - The input node, with its own inputs
+    ///
+    /// # Return
+    ///
+    /// * true whether a node has been found
     pub fn add_input_to<F2>(&mut self, name: &'static str, input: Node<S, F2, N>) -> bool
     where
         F2: Process<S> + Clone + 'static,
     {
-        let input_name = input.name;
-        let input = Arc::new(Mutex::new(input));
-
-        // 1. add to the hierarchy
-        let node_found = if let Some(node) = self.nodes.get_mut(name) {
-            // We found a node
-            let mut node = node.lock().unwrap();
-
-            node.add_input_trait_object(input_name, input.clone());
-
-            true
-        } else {
-            false
-        };
-
-        // 2. add to the graph hash map
-        if node_found {
-            self.nodes.insert(input_name, input);
-        }
+        self.add_boxed_input(name, Box::new(input))
+    }
 
-        node_found
+    /// Connect a node of the graph as an input of another node of the graph
+    ///
+    /// A node can be the input of several nodes. Its output is computed
+    /// once per sample and given to all of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - The name of the node to use as input
+    /// * `dst` - The name of the node receiving the input
+    ///
+    /// # Return
+    ///
+    /// * true whether the connection has been made. A connection is rejected if one of the nodes
+    ///   is not found, if it already exists or if it would create a cycle in the graph
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use audio_graph::{Watcher, Audiograph, Node};
+    /// use audio_graph::{Mixer, Multiplier, SineWave};
+    /// let lfo = Node::new("lfo", SineWave::new(1.0, 10.0));
+    /// let mut m1 = Node::new("m1", Multiplier);
+    /// m1.add_input(lfo).add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)));
+    /// let mut m2 = Node::new("m2", Multiplier);
+    /// m2.add_input(Node::new("sw2", SineWave::new(0.1, 5000.0)));
+    /// let mut mixer = Node::new("mixer", Mixer);
+    /// mixer.add_input(m1).add_input(m2);
+    ///
+    /// let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+    /// // The lfo modulates both multipliers
+    /// assert!(audio.connect("lfo", "m2"));
+    /// // A node cannot be the input of one of its inputs
+    /// assert!(!audio.connect("mixer", "lfo"));
+    /// let mut buf = Box::new([0.0; 1000]);
+    /// audio.stream_into(&mut buf, true);
+    /// ```
+    pub fn connect(&mut self, src: &'static str, dst: &'static str) -> bool {
+        self.plan.connect(src, dst)
     }
 
     /// Register an event to a node by its name
@@ -139,24 +227,32 @@ where
     where
         F: Process<S> + Clone + 'static,
     {
-        if let Some(node) = self.nodes.get_mut(name) {
+        if let Some(node) = self.plan.get_mut(name) {
             // We found a node
-            let mut node = node.lock().unwrap();
-
             if let Some(node) = node.as_mut_any().downcast_mut::<Node<S, F, N>>() {
-                node.register_event(event);
+                // Events changing the topology are played by the graph
+                match event.into_graph_event(name) {
+                    Ok(event) => self.schedule(event),
+                    Err(event) => node.register_event(event),
+                }
 
-                true
-            } else {
-                false
+                return true;
             }
-        } else {
-            false
         }
+
+        // The node carried by a rejected event is sent back to the handle, if any,
+        // rather than deallocated on the thread rendering the graph
+        if let Some(node) = event.into_node() {
+            self.plan.removed().push(node);
+        }
+        false
     }
 
     /// Delete a node by its name
     ///
+    /// The node is disconnected from all the nodes it is an input of. Its inputs
+    /// are deleted as well unless they are also the inputs of other nodes.
+    ///
     /// # Arguments
     ///
     /// * `name` - The name of the root to delete
@@ -165,13 +261,7 @@ where
     ///
     /// * true whether a node has been found
     pub fn delete_node(&mut self, name: &'static str) -> bool {
-        let mut nodes_to_remove = HashSet::new();
-
-        let node_found = self.root.delete_node(name, &mut nodes_to_remove);
-
-        self.nodes.retain(|name, _| !nodes_to_remove.contains(name));
-
-        node_found
+        self.plan.delete_node(name)
     }
 
     /// Stream the next N samples into a buffer of size N allocated on the heap
@@ -179,9 +269,10 @@ where
     /// # Arguments
     ///
     /// * `buf` - The buffer to fill
-    /// * `multithreading` - Enable multithreading. The streaming of the parent nodes is multithreaded.
-    ///   Each parent buffer is filled in a separate thread. Once all the parents buffers are computed,
-    ///   we compute the root buffer in the main thread.
+    /// * `multithreading` - Enable multithreading. The nodes that do not depend on each other
+    ///   are processed in parallel by the workers of the graph. Nodes are processed level by level:
+    ///   the nodes of a level are processed once all their inputs from the previous levels are computed.
+    ///   Ignored if the graph has been created with no workers.
     ///
     /// # Example
     ///
@@ -206,16 +297,258 @@ where
     /// audio.stream_into(&mut buf, true);
     /// ```
     pub fn stream_into(&mut self, buf: &mut Box<[S; N]>, multithreading: bool) {
-        self.root.stream_into(buf, multithreading);
+        self.stream_into_rtrb(multithreading);
+
+        buf.copy_from_slice(self.plan.output());
     }
 
+    /// Stream the next N samples into the buffers of the graph
+    ///
+    /// Every node streams into its own buffer. A node reads the values of its inputs
+    /// from their buffers, in the order the inputs have been added, so that the result
+    /// does not depend on the thread a node has been processed on.
+    /// The output of the graph can be retrieved with `get_buf`.
+    ///
+    /// # Arguments
+    ///
+    /// * `multithreading` - Enable multithreading. See `stream_into`.
     pub fn stream_into_rtrb(&mut self, multithreading: bool) {
-        self.root.stream_into_rtrb(multithreading);
+        self.apply_commands();
+
+        let ctx = self.context(N);
+        let mut start = 0;
+        while start < N {
+            let range = self.play_graph_events(start);
+
+            let pool = self.pool.as_ref().filter(|_| multithreading);
+            self.plan.render(&ctx, range.clone(), pool);
+            start = range.end;
+        }
+
+        self.advance(&ctx);
+    }
+
+    /// Render the graph on a dedicated thread into a ring buffer
+    ///
+    /// The blocks are streamed with multithreading enabled and pushed into the ring buffer
+    /// as soon as there is room for them. The samples are pulled lock-free from the returned
+    /// render thread, for instance from an audio callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of samples the ring buffer can hold. It is at least N
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use audio_graph::{Watcher, Audiograph, Node};
+    /// use audio_graph::SineWave;
+    /// let sw1 = Node::new("sinewave", SineWave::new(0.1, 2500.0));
+    /// let audio = Audiograph::<_, 64>::new(44100.0, Watcher::on(sw1));
+    /// let mut render = audio.spawn_render_thread(1024);
+    /// let mut buf = [0.0; 256];
+    /// render.read_into(&mut buf);
+    /// // Get the graph back
+    /// let audio = render.stop();
+    /// ```
+    pub fn spawn_render_thread(self, capacity: usize) -> RenderThread<S, N> {
+        RenderThread::spawn(self, capacity)
+    }
+
+    /// Create a handle to modify the graph from another thread
+    ///
+    /// The commands sent through the handle are applied at the start of the next block,
+    /// whatever the streaming method. Only the last created handle is listened to.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of commands that can be waiting to be applied
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use audio_graph::{Watcher, Audiograph, Node, Event};
+    /// use audio_graph::{Mixer, SineWave};
+    /// let mut mixer = Node::new("mixer", Mixer);
+    /// mixer.add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)));
+    /// let mut audio = Audiograph::<_, 64>::new(44100.0, Watcher::on(mixer));
+    /// let mut handle = audio.handle(16);
+    /// let mut render = audio.spawn_render_thread(1024);
+    ///
+    /// // Control the graph while it is rendered
+    /// assert!(handle.add_input_to("mixer", Node::new("sw2", SineWave::new(0.1, 5000.0))));
+    /// let event = Event::note_off(std::time::Duration::from_millis(100), &handle);
+    /// assert!(handle.register_event::<SineWave>("sw1", event));
+    /// assert!(handle.delete_node("sw2"));
+    ///
+    /// let mut buf = [0.0; 256];
+    /// render.read_into(&mut buf);
+    /// handle.collect_garbage();
+    /// ```
+    pub fn handle(&mut self, capacity: usize) -> AudiographHandle<S, N> {
+        let (commands, commands_consumer) = RingBuffer::new(capacity);
+        // Every command may remove nodes. Those that do not fit are sent back at the next blocks
+        let (trash_producer, trash) = RingBuffer::new(4 * capacity.max(N));
+
+        self.commands = Some(commands_consumer);
+        self.trash = Some(trash_producer);
+
+        AudiographHandle::new(self.sample_rate, commands, trash)
+    }
+
+    /// Get the last block of N samples streamed with `stream_into_rtrb`
+    pub fn get_buf(&self) -> &[S; N] {
+        self.plan.output()
+    }
+
+    /// Get the transport position of the graph
+    ///
+    /// This is the absolute index of the next sample to be streamed.
+    /// It starts at 0 and is advanced by N every time a block is streamed.
+    pub fn get_clock(&self) -> SampleIdx {
+        self.clock
+    }
+
+    /// Set the tempo given to the nodes, in beats per minute
+    ///
+    /// The musical position of the graph is kept, the new tempo applies from the next block
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.transport.tempo = tempo;
+    }
+
+    /// Get the tempo and the musical position of the next sample to be streamed
+    pub fn get_transport(&self) -> Transport {
+        self.transport
     }
 
     pub(crate) fn get_sampling_rate(&self) -> SamplingRate {
         self.sample_rate
     }
+
+    // The context of the next sample to be streamed, in a block of `block_size` samples
+    fn context(&self, block_size: usize) -> Context {
+        Context {
+            sample_rate: self.sample_rate.hz(),
+            sample: self.clock,
+            block_size,
+            transport: self.transport,
+        }
+    }
+
+    // Move the transport after the block streamed in the context `ctx`
+    fn advance(&mut self, ctx: &Context) {
+        let next = ctx.offset(ctx.block_size);
+        self.clock = next.sample;
+        self.transport = next.transport;
+    }
+
+    // Apply the commands sent by the handle and send the removed nodes back to it
+    //
+    // Nothing is dropped on the thread rendering the graph when there is a handle: the removed
+    // nodes that do not fit in the trash queue wait for the next block, and the commands wait
+    // in their queue until the nodes removed by the previous ones have been sent back.
+    fn apply_commands(&mut self) {
+        self.throw_removed();
+
+        if let Some(mut commands) = self.commands.take() {
+            while self.plan.removed().is_empty()
+                && self.trash.as_ref().is_none_or(|t| t.slots() > 0)
+            {
+                let mut command = match commands.pop() {
+                    Ok(command) => command,
+                    Err(_) => break,
+                };
+                command.apply(self);
+                if let Some(trash) = self.trash.as_mut() {
+                    // There is room for the command, checked before applying it
+                    let _ = trash.push(Trash::Command(command));
+                }
+                self.throw_removed();
+            }
+            self.commands = Some(commands);
+        }
+    }
+
+    // Send the removed nodes to the handle to be deallocated, as many as the trash queue holds.
+    // They are dropped here if there is no handle
+    fn throw_removed(&mut self) {
+        let removed = self.plan.removed();
+        match self.trash.as_mut() {
+            Some(trash) => {
+                while trash.slots() > 0 {
+                    match removed.pop() {
+                        Some(node) => {
+                            let _ = trash.push(Trash::Node(node));
+                        }
+                        None => break,
+                    }
+                }
+            }
+            None => removed.clear(),
+        }
+    }
+
+    fn schedule(&mut self, event: GraphEvent<S, N>) {
+        let sample = event.get_sample_idx();
+        // Keep the events sorted by decreasing sample indices. Events occuring
+        // at the same sample are played in their order of registration
+        let idx = self.events.partition_point(|e| e.get_sample_idx() > sample);
+        self.events.insert(idx, event);
+    }
+
+    fn schedule_all(&mut self, events: &mut Vec<GraphEvent<S, N>>) {
+        for event in events.drain(..) {
+            self.schedule(event);
+        }
+    }
+
+    // Add a node as an input of the node `name` and schedule the graph events it carries
+    fn add_boxed_input(&mut self, name: &'static str, input: Box<dyn NodeTrait<S, N>>) -> bool {
+        let mut events = std::mem::take(&mut self.added_events);
+        let node_found = self.plan.add_input(name, input, &mut events);
+        self.schedule_all(&mut events);
+        self.added_events = events;
+
+        node_found
+    }
+
+    // Play the graph events occuring at the sample `start` of the current block
+    // and return the range of samples that can be streamed before the next graph event
+    fn play_graph_events(&mut self, start: usize) -> Range<usize> {
+        let sample = SampleIdx(self.clock.0 + start);
+        while self
+            .events
+            .last()
+            .is_some_and(|event| event.get_sample_idx() <= sample)
+        {
+            let event = self.events.pop().unwrap();
+            self.play_graph_event(event);
+        }
+
+        let end = self
+            .events
+            .last()
+            .map_or(N, |event| (event.get_sample_idx().0 - self.clock.0).min(N));
+
+        start..end
+    }
+
+    fn play_graph_event(&mut self, event: GraphEvent<S, N>) {
+        match event {
+            GraphEvent::AddInput { to, input, .. } => {
+                self.add_boxed_input(to, input);
+            }
+            GraphEvent::RemoveInput { from, name, .. } => {
+                self.plan.remove_input(from, name);
+            }
+            GraphEvent::DeleteNode { name, .. } => {
+                self.plan.delete_node(name);
+            }
+            GraphEvent::Reconnect { name, from, to, .. } => {
+                self.plan.reconnect(name, from, to);
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -224,7 +557,7 @@ impl<S> Process<S> for Sentinel
 where
     S: rodio::Sample + Send + Sync + 'static,
 {
-    fn process_next_value(&mut self, inputs: &[S]) -> S {
+    fn process_next_value(&mut self, inputs: &[S], _: &Context) -> S {
         if let Some(s) = inputs.first() {
             *s
         } else {
@@ -283,6 +616,27 @@ where
     type Item = S;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.root.next()
+        // Stream the first sample of a block starting at the current sample
+        self.apply_commands();
+        self.play_graph_events(0);
+        let ctx = self.context(1);
+        self.plan.render(&ctx, 0..1, None);
+
+        self.advance(&ctx);
+
+        Some(self.plan.output()[0])
+    }
+}
+
+impl<S, const N: usize> Timeline<S, N> for Audiograph<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    fn sample_idx_at(&self, time: std::time::Duration) -> SampleIdx {
+        self.sample_rate.sample_idx_at(time)
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate.hz()
     }
 }
@@ -0,0 +1,182 @@
+use crate::event::Event;
+use crate::graph::Audiograph;
+use crate::node::{Node, NodeTrait, Process};
+use crate::sampling::{SampleIdx, SamplingRate, Timeline};
+
+use rtrb::{Consumer, Producer};
+
+// A change of the graph sent by the control handle, applied by the graph at the start of a block
+pub(crate) trait Command<S, const N: usize>: Send
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    // Apply the command on the graph. The command is sent back empty to the
+    // handle afterwards so that it is not deallocated on the audio thread
+    fn apply(&mut self, audio: &mut Audiograph<S, N>);
+}
+
+// A command calling a method of the graph on a node with an argument
+struct Apply<S, T, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    name: &'static str,
+    arg: Option<T>,
+    f: fn(&mut Audiograph<S, N>, &'static str, T) -> bool,
+}
+
+impl<S, T, const N: usize> Command<S, N> for Apply<S, T, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+    T: Send,
+{
+    fn apply(&mut self, audio: &mut Audiograph<S, N>) {
+        if let Some(arg) = self.arg.take() {
+            (self.f)(audio, self.name, arg);
+        }
+    }
+}
+
+// What the graph sends back to the handle to be deallocated
+pub(crate) enum Trash<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    Node(Box<dyn NodeTrait<S, N>>),
+    Command(Box<dyn Command<S, N>>),
+}
+
+/// A handle to modify a graph while it is rendered on another thread
+///
+/// The changes are pushed as commands into a wait-free queue. The graph applies them
+/// at the start of the next block it streams, so that the thread rendering the graph
+/// is never blocked. The nodes removed from the graph are sent back to the handle
+/// and deallocated on its thread.
+///
+/// The commands are applied in the order they are sent. As they are applied
+/// later on, the methods only tell whether the command has been queued.
+pub struct AudiographHandle<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    sample_rate: SamplingRate,
+    commands: Producer<Box<dyn Command<S, N>>>,
+    trash: Consumer<Trash<S, N>>,
+}
+
+impl<S, const N: usize> AudiographHandle<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    pub(crate) fn new(
+        sample_rate: SamplingRate,
+        commands: Producer<Box<dyn Command<S, N>>>,
+        trash: Consumer<Trash<S, N>>,
+    ) -> Self {
+        Self {
+            sample_rate,
+            commands,
+            trash,
+        }
+    }
+
+    /// Register an event to a node by its name. See `Audiograph::register_event`
+    ///
+    /// # Return
+    ///
+    /// * true whether the command has been queued, false if the queue is full
+    pub fn register_event<F>(&mut self, name: &'static str, event: Event<S, F, N>) -> bool
+    where
+        F: Process<S> + Clone + 'static,
+    {
+        self.send(name, event, Audiograph::register_event)
+    }
+
+    /// Add a node as an input of another node. See `Audiograph::add_input_to`
+    ///
+    /// # Return
+    ///
+    /// * true whether the command has been queued, false if the queue is full
+    pub fn add_input_to<F2>(&mut self, name: &'static str, mut input: Node<S, F2, N>) -> bool
+    where
+        F2: Process<S> + Clone + 'static,
+    {
+        // The buffers of the node are allocated here rather than on the thread rendering the graph
+        input.prepare(self.sample_rate.hz(), N);
+        self.send(name, input, Audiograph::add_input_to)
+    }
+
+    /// Connect a node of the graph as an input of another node. See `Audiograph::connect`
+    ///
+    /// # Return
+    ///
+    /// * true whether the command has been queued, false if the queue is full
+    pub fn connect(&mut self, src: &'static str, dst: &'static str) -> bool {
+        self.send(dst, src, |audio, dst, src| audio.connect(src, dst))
+    }
+
+    /// Delete a node by its name. See `Audiograph::delete_node`
+    ///
+    /// # Return
+    ///
+    /// * true whether the command has been queued, false if the queue is full
+    pub fn delete_node(&mut self, name: &'static str) -> bool {
+        self.send(name, (), |audio, name, _| audio.delete_node(name))
+    }
+
+    /// Deallocate the nodes removed from the graph and the applied commands
+    ///
+    /// It is called every time a command is sent. It should also be called
+    /// regularly when no command is sent so that the trash queue does not fill up,
+    /// the graph keeps the removed nodes and stops applying the commands otherwise.
+    ///
+    /// # Return
+    ///
+    /// * the number of deallocated nodes
+    pub fn collect_garbage(&mut self) -> usize {
+        let mut num_nodes = 0;
+        while let Ok(trash) = self.trash.pop() {
+            match trash {
+                Trash::Node(node) => {
+                    drop(node);
+                    num_nodes += 1;
+                }
+                Trash::Command(command) => drop(command),
+            }
+        }
+
+        num_nodes
+    }
+
+    fn send<T>(
+        &mut self,
+        name: &'static str,
+        arg: T,
+        f: fn(&mut Audiograph<S, N>, &'static str, T) -> bool,
+    ) -> bool
+    where
+        T: Send + 'static,
+    {
+        self.collect_garbage();
+
+        let command = Apply {
+            name,
+            arg: Some(arg),
+            f,
+        };
+        self.commands.push(Box::new(command)).is_ok()
+    }
+}
+
+impl<S, const N: usize> Timeline<S, N> for AudiographHandle<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    fn sample_idx_at(&self, time: std::time::Duration) -> SampleIdx {
+        self.sample_rate.sample_idx_at(time)
+    }
+
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate.hz()
+    }
+}
@@ -5,35 +5,72 @@
 //! - Provide traits for the user to implement its own nodes (through the trait Process, and Params)
 //! - Use of generics to be compatible with rodio Sample trait
 //! - Parallel streaming into a buffer of size N
-//! - Events handling and triggered at a specific sample indices (add new node/delete nodes are not implemented)
+//! - Real-time rendering on a dedicated thread into a lock-free ring buffer
+//! - Control of the graph from another thread through a wait-free command queue
+//! - Events handling and triggered at a specific sample indices, including adding, removing and moving nodes in the graph
 extern crate rtrb;
 
 mod node;
 pub use node::Node;
-pub use node::{Mixer, Multiplier, SineWave};
+pub use node::{Adsr, AdsrCurve};
+pub use node::{Biquad, BiquadKind, LadderFilter, StateVariableFilter};
+pub use node::{BrownNoise, PinkNoise, WhiteNoise};
+pub use node::{Chorus, Flanger, Phaser};
+pub use node::{Compressor, Expander, Gate, Limiter};
+pub use node::{Convolver, Delay, DelayInterpolation, Reverb};
+pub use node::{Mixer, Multiplier, PulseWave, SawWave, SineWave, SquareWave, TriangleWave};
+pub use node::{Modulation, Wavetable};
 
 mod sampling;
+pub use sampling::{SampleIdx, Timeline};
+
+mod fft;
+
+mod context;
+pub use context::{Context, Transport};
+
+mod plan;
 
 mod graph;
 pub use graph::Audiograph;
 pub use graph::Watcher;
 
+mod render_thread;
+pub use render_thread::{RenderStats, RenderThread};
+
+mod handle;
+pub use handle::AudiographHandle;
+
 mod event;
-pub use event::Event;
+pub use event::{Event, RampCurve};
 
 #[cfg(test)]
 mod tests {
     use super::node::*;
-    use super::{Audiograph, Event, Node, Watcher};
+    use super::{
+        Audiograph, Context, Event, Node, RampCurve, SampleIdx, Timeline, Transport, Watcher,
+    };
     use rodio::{OutputStream, Sink};
+    use std::time::Duration;
 
     const NUM_SAMPLES: usize = 64;
+    // Context to compute the expected values of the nodes
+    const CTX: Context = Context {
+        sample_rate: 44100.0,
+        sample: SampleIdx(0),
+        block_size: 1,
+        transport: Transport {
+            tempo: 120.0,
+            beat: 0.0,
+        },
+    };
 
     fn create_empty_buffer<const N: usize>() -> Box<[f32; N]> {
         let buf = vec![0.0; N].into_boxed_slice();
         unsafe { Box::from_raw(Box::into_raw(buf) as *mut [f32; N]) }
     }
 
+    #[allow(dead_code, clippy::boxed_local)]
     fn play_sound<const N: usize>(buf: Box<[f32; N]>) {
         let (_stream, stream_handle) = OutputStream::try_default().unwrap();
         let sink = Sink::try_new(&stream_handle).unwrap();
@@ -46,6 +83,60 @@ mod tests {
         sink.sleep_until_end();
     }
 
+    // Stream `num_blocks` blocks of the graph one after the other
+    fn render<const N: usize>(
+        audio: &mut Audiograph<f32, N>,
+        num_blocks: usize,
+        multithreading: bool,
+    ) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(num_blocks * N);
+
+        let mut buf = create_empty_buffer::<N>();
+        for _ in 0..num_blocks {
+            audio.stream_into(&mut buf, multithreading);
+            samples.extend_from_slice(&buf[..]);
+        }
+
+        samples
+    }
+
+    // An input of the node rendered by `render_process`, shared so that the node can be cloned
+    #[derive(Clone)]
+    struct Input(std::sync::Arc<std::sync::Mutex<Box<dyn Process<f32>>>>);
+    impl Process<f32> for Input {
+        fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+            self.0.lock().unwrap().process_next_value(inputs, ctx)
+        }
+
+        fn prepare(&mut self, sample_rate: f32, max_block: usize) {
+            self.0.lock().unwrap().prepare(sample_rate, max_block);
+        }
+    }
+
+    // Render `num_blocks` blocks of the node "node" processing `f`, fed by `inputs` in this
+    // order, in a graph at `sample_rate`. The graph is given back to register events
+    // or render more blocks
+    fn render_process<F>(
+        f: F,
+        inputs: Vec<Box<dyn Process<f32>>>,
+        sample_rate: f32,
+        num_blocks: usize,
+    ) -> (Audiograph<f32, NUM_SAMPLES>, Vec<f32>)
+    where
+        F: Process<f32> + Clone + Send + Sync + 'static,
+    {
+        let mut node = Node::new("node", f);
+        for input in inputs {
+            node.add_input(Node::new(
+                "input",
+                Input(std::sync::Arc::new(std::sync::Mutex::new(input))),
+            ));
+        }
+        let mut audio = Audiograph::new(sample_rate, Watcher::on(node));
+        let samples = render(&mut audio, num_blocks, false);
+        (audio, samples)
+    }
+
     #[test]
     fn simple_sinewave_graph() {
         let sw1 = Node::new("sinewave", SineWave::new(0.1, 2500.0));
@@ -307,4 +398,2422 @@ mod tests {
         let mut buf = create_empty_buffer::<NUM_SAMPLES>();
         audio.stream_into(&mut buf, true);
     }
+
+    #[test]
+    fn clock_advances_by_block() {
+        let sw1 = Node::<_, _, NUM_SAMPLES>::new("sw1", SineWave::new(0.1, 2500.0));
+        let mut audio = Audiograph::new(44100.0, Watcher::on(sw1));
+        assert_eq!(audio.get_clock(), SampleIdx(0));
+
+        render(&mut audio, 10, false);
+        assert_eq!(audio.get_clock(), SampleIdx(10 * NUM_SAMPLES));
+    }
+
+    #[test]
+    fn event_fires_at_absolute_sample() {
+        for multithreading in [false, true] {
+            let sw1 = Node::<_, _, NUM_SAMPLES>::new("sw1", SineWave::new(0.1, 2500.0));
+            let sw2 = Node::new("sw2", SineWave::new(0.1, 9534.0));
+            let mut mixer = Node::new("mixer", Mixer);
+            mixer.add_input(sw1).add_input(sw2);
+
+            let sampling_rate = 44100.0;
+            let mut audio = Audiograph::new(sampling_rate, Watcher::on(mixer));
+
+            // Mute the nodes at 2s and 2.5s. 2.5s does not fall on a block boundary
+            let e1 = Event::update_params(
+                |f: &mut SineWave| f.params.ampl = 0.0,
+                std::time::Duration::new(2, 0),
+                &audio,
+            );
+            assert!(audio.register_event("sw1", e1));
+            let e2 = Event::update_params(
+                |f: &mut SineWave| f.params.ampl = 0.0,
+                std::time::Duration::from_millis(2500),
+                &audio,
+            );
+            assert!(audio.register_event("sw2", e2));
+
+            // Render 3 seconds
+            let num_blocks = 3 * 44100 / NUM_SAMPLES;
+            let samples = render(&mut audio, num_blocks, multithreading);
+
+            let (idx_e1, idx_e2) = (88200, 110250);
+            assert_ne!(idx_e1 % NUM_SAMPLES, 0);
+            assert_ne!(idx_e2 % NUM_SAMPLES, 0);
+
+            // The events must not have been fired in the first blocks
+            assert!(samples[..NUM_SAMPLES].iter().any(|s| *s != 0.0));
+            // Both sinewaves are playing until the first event
+            assert!(samples[idx_e1 - 1] != 0.0);
+            // Only sw2 is playing between the two events
+            let mut sw2 = SineWave::new(0.1, 9534.0);
+            let expected = (0..idx_e2)
+                .map(|_| sw2.process_next_value(&[], &CTX))
+                .collect::<Vec<_>>();
+            assert_eq!(&samples[idx_e1..idx_e2], &expected[idx_e1..idx_e2]);
+            // Everything is muted after the second event
+            assert!(samples[idx_e2..].iter().all(|s| *s == 0.0));
+        }
+    }
+
+    // Build a graph with events on the sources, the multiplier and the mixer
+    fn conformance_graph() -> Audiograph<f32, NUM_SAMPLES> {
+        let sw1 = Node::new("sw1", SineWave::new(0.1, 2500.0));
+        let sw2 = Node::new("sw2", SineWave::new(0.2, 9534.0));
+        let lfo = Node::new("lfo", SineWave::new(1.0, 10.0));
+        let sw3 = Node::new("sw3", SineWave::new(0.3, 1200.0));
+        let mut mult = Node::new("multiplier", Multiplier);
+        mult.add_input(lfo).add_input(sw3);
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(sw1).add_input(sw2).add_input(mult);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+
+        for (name, ms) in [("sw1", 100), ("sw2", 333), ("lfo", 517), ("sw3", 1001)] {
+            let event = Event::update_params(
+                |f: &mut SineWave| {
+                    f.params.freq *= 1.1;
+                },
+                std::time::Duration::from_millis(ms),
+                &audio,
+            );
+            assert!(audio.register_event(name, event));
+        }
+        let e1 = Event::<_, Multiplier, NUM_SAMPLES>::note_off(
+            std::time::Duration::from_millis(250),
+            &audio,
+        );
+        assert!(audio.register_event("multiplier", e1));
+        let e2 = Event::<_, Multiplier, NUM_SAMPLES>::note_on(
+            std::time::Duration::from_millis(601),
+            &audio,
+        );
+        assert!(audio.register_event("multiplier", e2));
+        let e3 = Event::<_, Mixer, NUM_SAMPLES>::note_off(
+            std::time::Duration::from_millis(1111),
+            &audio,
+        );
+        assert!(audio.register_event("mixer", e3));
+
+        let sw4 = Node::new("sw4", SineWave::new(0.2, 3000.0));
+        let e4 = Event::<_, Mixer, NUM_SAMPLES>::add_input(
+            sw4,
+            std::time::Duration::from_millis(707),
+            &audio,
+        );
+        assert!(audio.register_event("mixer", e4));
+
+        audio
+    }
+
+    #[test]
+    fn streaming_methods_conformance() {
+        let num_blocks = 44100 * 5 / 4 / NUM_SAMPLES;
+        let num_samples = num_blocks * NUM_SAMPLES;
+
+        let sequential = render(&mut conformance_graph(), num_blocks, false);
+        let parallel = render(&mut conformance_graph(), num_blocks, true);
+
+        let mut audio = conformance_graph();
+        let mut rtrb = Vec::with_capacity(num_samples);
+        for _ in 0..num_blocks {
+            audio.stream_into_rtrb(false);
+            rtrb.extend_from_slice(audio.get_buf());
+        }
+
+        let iterator = conformance_graph().take(num_samples).collect::<Vec<_>>();
+
+        // The mixer has been switched off at 1.111s
+        assert!(sequential[..48000].iter().any(|s| *s != 0.0));
+        assert!(sequential[49000..].iter().all(|s| *s == 0.0));
+
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential, rtrb);
+        assert_eq!(sequential, iterator);
+    }
+
+    #[test]
+    fn event_add_node_at_sample() {
+        let sw1 = Node::<_, _, NUM_SAMPLES>::new("sw1", SineWave::new(0.1, 2500.0));
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(sw1);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+
+        let sw2 = Node::new("sw2", SineWave::new(0.1, 5000.0));
+        let sw3 = Node::new("sw3", SineWave::new(0.1, 7000.0));
+        let mut bus = Node::new("bus", Mixer);
+        bus.add_input(sw2).add_input(sw3);
+
+        let event =
+            Event::<f32, Mixer, NUM_SAMPLES>::add_input(bus, Duration::from_millis(1500), &audio);
+        assert!(audio.register_event("mixer", event));
+
+        // The nodes are not in the graph yet
+        let mute = Event::update_params(
+            |f: &mut SineWave| f.params.ampl = 0.0,
+            Duration::new(3, 0),
+            &audio,
+        );
+        assert!(!audio.register_event("sw3", mute));
+
+        let num_blocks = 2 * 44100 / NUM_SAMPLES;
+        let samples = render(&mut audio, num_blocks, false);
+
+        // The bus is added at sample 66150 which is in the middle of a block
+        let idx_event = 66150;
+        assert_ne!(idx_event % NUM_SAMPLES, 0);
+
+        let mut sw1 = SineWave::new(0.1, 2500.0);
+        let mut sw2 = SineWave::new(0.1, 5000.0);
+        let mut sw3 = SineWave::new(0.1, 7000.0);
+        let expected = (0..samples.len())
+            .map(|idx| {
+                let s1 = sw1.process_next_value(&[], &CTX);
+                if idx < idx_event {
+                    Mixer.process_next_value(&[s1], &CTX)
+                } else {
+                    let bus = Mixer.process_next_value(
+                        &[
+                            sw2.process_next_value(&[], &CTX),
+                            sw3.process_next_value(&[], &CTX),
+                        ],
+                        &CTX,
+                    );
+                    Mixer.process_next_value(&[s1, bus], &CTX)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(samples, expected);
+
+        // The added nodes can now be targeted by events
+        let mute = Event::update_params(
+            |f: &mut SineWave| f.params.ampl = 0.0,
+            Duration::new(3, 0),
+            &audio,
+        );
+        assert!(audio.register_event("sw3", mute));
+    }
+
+    #[test]
+    fn scheduled_removals() {
+        let sw1 = Node::<_, _, NUM_SAMPLES>::new("sw1", SineWave::new(0.1, 2500.0));
+        let sw2 = Node::new("sw2", SineWave::new(0.1, 5000.0));
+        let sw3 = Node::new("sw3", SineWave::new(0.1, 7000.0));
+        let sw4 = Node::new("sw4", SineWave::new(0.1, 300.0));
+        let mut bus = Node::new("bus", Mixer);
+        bus.add_input(sw2).add_input(sw3);
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(sw1).add_input(bus).add_input(sw4);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+
+        let e1 =
+            Event::<_, Mixer, NUM_SAMPLES>::remove_input("bus", Duration::from_millis(500), &audio);
+        assert!(audio.register_event("mixer", e1));
+        let e2 = Event::<_, SineWave, NUM_SAMPLES>::delete_node(Duration::new(1, 0), &audio);
+        assert!(audio.register_event("sw4", e2));
+
+        let num_blocks = 44100 * 5 / 4 / NUM_SAMPLES;
+        let samples = render(&mut audio, num_blocks, false);
+
+        let (idx_e1, idx_e2) = (22050, 44100);
+        let mut sw1 = SineWave::new(0.1, 2500.0);
+        let mut sw2 = SineWave::new(0.1, 5000.0);
+        let mut sw3 = SineWave::new(0.1, 7000.0);
+        let mut sw4 = SineWave::new(0.1, 300.0);
+        let expected = (0..samples.len())
+            .map(|idx| {
+                let mut inputs = vec![sw1.process_next_value(&[], &CTX)];
+                if idx < idx_e1 {
+                    inputs.push(Mixer.process_next_value(
+                        &[
+                            sw2.process_next_value(&[], &CTX),
+                            sw3.process_next_value(&[], &CTX),
+                        ],
+                        &CTX,
+                    ));
+                }
+                if idx < idx_e2 {
+                    inputs.push(sw4.process_next_value(&[], &CTX));
+                }
+                Mixer.process_next_value(&inputs, &CTX)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(samples, expected);
+
+        // The removed nodes are not in the graph anymore
+        for name in ["bus", "sw2", "sw3", "sw4"] {
+            let event = Event::<_, SineWave, NUM_SAMPLES>::note_off(Duration::new(2, 0), &audio);
+            assert!(!audio.register_event(name, event));
+        }
+    }
+
+    #[test]
+    fn scheduled_reconnection() {
+        let sw1 = Node::<_, _, NUM_SAMPLES>::new("sw1", SineWave::new(0.1, 2500.0));
+        let lfo = Node::new("lfo", SineWave::new(1.0, 10.0));
+        let mut mult = Node::new("multiplier", Multiplier);
+        mult.add_input(lfo);
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(sw1).add_input(mult);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+
+        // Move sw1 so that it is modulated by the lfo
+        let e1 = Event::<_, SineWave, NUM_SAMPLES>::reconnect(
+            "mixer",
+            "multiplier",
+            Duration::from_millis(500),
+            &audio,
+        );
+        assert!(audio.register_event("sw1", e1));
+        // Moving the multiplier into its own input would create a cycle, it is not played
+        let e2 = Event::<_, Multiplier, NUM_SAMPLES>::reconnect(
+            "mixer",
+            "lfo",
+            Duration::from_millis(700),
+            &audio,
+        );
+        assert!(audio.register_event("multiplier", e2));
+
+        let num_blocks = 44100 / NUM_SAMPLES;
+        let samples = render(&mut audio, num_blocks, false);
+
+        let idx_event = 22050;
+        let mut sw1 = SineWave::new(0.1, 2500.0);
+        let mut lfo = SineWave::new(1.0, 10.0);
+        let expected = (0..samples.len())
+            .map(|idx| {
+                let s1 = sw1.process_next_value(&[], &CTX);
+                let l = lfo.process_next_value(&[], &CTX);
+                if idx < idx_event {
+                    Mixer.process_next_value(&[s1, Multiplier.process_next_value(&[l], &CTX)], &CTX)
+                } else {
+                    Mixer.process_next_value(&[Multiplier.process_next_value(&[l, s1], &CTX)], &CTX)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn cycle_checks_on_diamonds() {
+        // Levels of two mixers both fed by the two mixers of the previous level. The paths
+        // from the top to the bottom double at each level
+        const NUM_LEVELS: usize = 40;
+        let names = (0..NUM_LEVELS)
+            .map(|level| {
+                let a: &'static str = Box::leak(format!("a{}", level).into_boxed_str());
+                let b: &'static str = Box::leak(format!("b{}", level).into_boxed_str());
+                (a, b)
+            })
+            .collect::<Vec<_>>();
+
+        let mut out = Node::<_, _, NUM_SAMPLES>::new("out", Mixer);
+        out.add_input(Node::new("sine", SineWave::new(0.1, 440.0)));
+        let mut audio = Audiograph::new(44100.0, Watcher::on(out));
+        for (a, b) in names.iter() {
+            assert!(audio.add_input_to("out", Node::new(a, Mixer)));
+            assert!(audio.add_input_to("out", Node::new(b, Mixer)));
+        }
+        assert!(audio.connect("sine", names[0].0));
+        assert!(audio.connect("sine", names[0].1));
+        for level in 1..NUM_LEVELS {
+            let (a, b) = names[level];
+            let (previous_a, previous_b) = names[level - 1];
+            for (src, dst) in [
+                (previous_a, a),
+                (previous_b, a),
+                (previous_a, b),
+                (previous_b, b),
+            ] {
+                assert!(audio.connect(src, dst));
+            }
+        }
+
+        // The bottom depends on the top through all the levels
+        let (top, bottom) = (names[NUM_LEVELS - 1].0, names[0].1);
+        assert!(!audio.connect(top, bottom));
+        // The same check runs when a reconnection is played while rendering
+        let event =
+            Event::<_, Mixer, NUM_SAMPLES>::reconnect("out", bottom, Duration::ZERO, &audio);
+        assert!(audio.register_event(top, event));
+        let samples = render(&mut audio, 1, false);
+        assert!(samples.iter().all(|s| s.is_finite()));
+    }
+
+    // Count the number of processed samples
+    #[derive(Clone)]
+    struct Counter(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+    impl Process<f32> for Counter {
+        fn process_next_value(&mut self, inputs: &[f32], _: &Context) -> f32 {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            inputs.iter().sum::<f32>()
+        }
+    }
+
+    #[test]
+    fn nodes_processed_once_per_sample() {
+        let counters = (0..4)
+            .map(|_| std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0)))
+            .collect::<Vec<_>>();
+        let count = |idx: usize| counters[idx].load(std::sync::atomic::Ordering::Relaxed);
+
+        let mut c0 = Node::<_, _, NUM_SAMPLES>::new("c0", Counter(counters[0].clone()));
+        let mut c1 = Node::new("c1", Counter(counters[1].clone()));
+        let mut c2 = Node::new("c2", Counter(counters[2].clone()));
+        c2.add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)));
+        c1.add_input(c2);
+        c0.add_input(c1);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(c0));
+        render(&mut audio, 10, true);
+        for idx in 0..3 {
+            assert_eq!(count(idx), 10 * NUM_SAMPLES);
+        }
+
+        // Adding a node in the middle of a block recompiles the plan
+        let c3 = Node::new("c3", Counter(counters[3].clone()));
+        let event = Event::<_, Counter, NUM_SAMPLES>::add_input(
+            c3,
+            Duration::from_secs_f32(20.5 * NUM_SAMPLES as f32 / 44100.0),
+            &audio,
+        );
+        assert!(audio.register_event("c1", event));
+        render(&mut audio, 20, false);
+        for idx in 0..3 {
+            assert_eq!(count(idx), 30 * NUM_SAMPLES);
+        }
+        assert_eq!(count(3), 9 * NUM_SAMPLES + NUM_SAMPLES / 2);
+
+        // Removing it stops its processing
+        assert!(audio.delete_node("c3"));
+        render(&mut audio, 1, false);
+        assert_eq!(count(0), 31 * NUM_SAMPLES);
+        assert_eq!(count(3), 9 * NUM_SAMPLES + NUM_SAMPLES / 2);
+    }
+
+    #[test]
+    fn shared_node_fan_out() {
+        let counter = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let mut lfo = Node::<_, _, NUM_SAMPLES>::new("lfo", Counter(counter.clone()));
+        lfo.add_input(Node::new("sine", SineWave::new(1.0, 10.0)));
+        let mut m1 = Node::new("m1", Multiplier);
+        m1.add_input(lfo)
+            .add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)));
+        let mut m2 = Node::new("m2", Multiplier);
+        m2.add_input(Node::new("sw2", SineWave::new(0.1, 5000.0)));
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(m1).add_input(m2);
+
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+        assert!(audio.connect("lfo", "m2"));
+
+        // Connections creating a cycle or already made are rejected
+        assert!(!audio.connect("m2", "lfo"));
+        assert!(!audio.connect("mixer", "sine"));
+        assert!(!audio.connect("lfo", "lfo"));
+        assert!(!audio.connect("lfo", "m2"));
+        assert!(!audio.connect("lfo", "unknown"));
+
+        let num_blocks = 100;
+        let samples = render(&mut audio, num_blocks, false);
+
+        // The lfo is processed once per sample
+        let count = || counter.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(count(), num_blocks * NUM_SAMPLES);
+
+        let mut sine = SineWave::new(1.0, 10.0);
+        let mut sw1 = SineWave::new(0.1, 2500.0);
+        let mut sw2 = SineWave::new(0.1, 5000.0);
+        let expected = (0..samples.len())
+            .map(|_| {
+                let lfo = sine.process_next_value(&[], &CTX);
+                let m1 =
+                    Multiplier.process_next_value(&[lfo, sw1.process_next_value(&[], &CTX)], &CTX);
+                let m2 =
+                    Multiplier.process_next_value(&[sw2.process_next_value(&[], &CTX), lfo], &CTX);
+                Mixer.process_next_value(&[m1, m2], &CTX)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(samples, expected);
+
+        // The lfo still feeds m2 once removed from m1
+        let event = Event::<_, Multiplier, NUM_SAMPLES>::remove_input(
+            "lfo",
+            Duration::from_millis(10),
+            &audio,
+        );
+        assert!(audio.register_event("m1", event));
+        render(&mut audio, 1, false);
+        assert!(audio.connect("lfo", "m1"));
+
+        // Deleting it disconnects it from all the multipliers
+        assert!(audio.delete_node("lfo"));
+        render(&mut audio, 1, false);
+        assert_eq!(count(), (num_blocks + 1) * NUM_SAMPLES);
+        assert!(!audio.connect("sine", "m1"));
+    }
+
+    // A mixer of `width` buses, each one mixing `width` sinewaves
+    fn wide_graph(width: usize) -> Watcher<f32, NUM_SAMPLES> {
+        let mut mixer = Node::new("mixer", Mixer);
+        for i in 0..width {
+            let name: &'static str = Box::leak(format!("bus{}", i).into_boxed_str());
+            let mut bus = Node::new(name, Mixer);
+            for j in 0..width {
+                let name: &'static str = Box::leak(format!("sw{}_{}", i, j).into_boxed_str());
+                let freq = 100.0 * (1 + i * width + j) as f32;
+                bus.add_input(Node::new(name, SineWave::new(0.01, freq)));
+            }
+            mixer.add_input(bus);
+        }
+
+        Watcher::on(mixer)
+    }
+
+    #[test]
+    fn parallel_rendering_on_workers() {
+        let graph = |num_workers| {
+            let mut audio = Audiograph::with_workers(44100.0, wide_graph(8), num_workers);
+            assert!(audio.connect("sw0_0", "bus5"));
+            assert!(audio.connect("sw0_0", "bus7"));
+            assert!(audio.delete_node("sw0_1"));
+            audio
+        };
+
+        let num_blocks = 50;
+        let sequential = render(&mut graph(0), num_blocks, false);
+
+        for num_workers in [0, 1, 2, 4, 7] {
+            let parallel = render(&mut graph(num_workers), num_blocks, true);
+            assert_eq!(sequential, parallel);
+        }
+    }
+
+    // Weight the inputs by their index so that the output depends on their order
+    #[derive(Clone)]
+    struct Weighted;
+    impl Process<f32> for Weighted {
+        fn process_next_value(&mut self, inputs: &[f32], _: &Context) -> f32 {
+            inputs
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| value * (idx + 1) as f32)
+                .sum::<f32>()
+        }
+    }
+
+    #[test]
+    fn rtrb_many_inputs_stress() {
+        const NUM_INPUTS: usize = 100;
+        let freqs = (0..NUM_INPUTS)
+            .map(|idx| 50.0 + 37.0 * idx as f32)
+            .collect::<Vec<_>>();
+
+        let graph = || {
+            let mut weighted = Node::new("weighted", Weighted);
+            for (idx, freq) in freqs.iter().enumerate() {
+                let name: &'static str = Box::leak(format!("sw{}", idx).into_boxed_str());
+                weighted.add_input(Node::new(name, SineWave::new(0.01, *freq)));
+            }
+            Audiograph::<_, NUM_SAMPLES>::with_workers(44100.0, Watcher::on(weighted), 8)
+        };
+
+        let num_blocks = 20;
+        let rtrb = |multithreading| {
+            let mut audio = graph();
+            let mut samples = Vec::with_capacity(num_blocks * NUM_SAMPLES);
+            for _ in 0..num_blocks {
+                audio.stream_into_rtrb(multithreading);
+                samples.extend_from_slice(audio.get_buf());
+            }
+            samples
+        };
+
+        let sequential = rtrb(false);
+        for _ in 0..10 {
+            assert_eq!(sequential, rtrb(true));
+        }
+
+        // Each input has been given at its own slot
+        let mut sws = freqs
+            .iter()
+            .map(|freq| SineWave::new(0.01, *freq))
+            .collect::<Vec<_>>();
+        let expected = (0..num_blocks * NUM_SAMPLES)
+            .map(|_| {
+                let inputs = sws
+                    .iter_mut()
+                    .map(|sw| sw.process_next_value(&[], &CTX))
+                    .collect::<Vec<_>>();
+                Weighted.process_next_value(&inputs, &CTX)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sequential, expected);
+    }
+
+    #[test]
+    fn render_thread_simulated_callback() {
+        let graph = || {
+            let mut mixer = Node::<_, _, NUM_SAMPLES>::new("mixer", Mixer);
+            mixer
+                .add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)))
+                .add_input(Node::new("sw2", SineWave::new(0.02, 9534.0)));
+            Audiograph::new(44100.0, Watcher::on(mixer))
+        };
+
+        let capacity = 16 * NUM_SAMPLES;
+        let mut thread = graph().spawn_render_thread(capacity);
+        let stats = thread.stats();
+
+        // Wait for the render thread to fill the ring buffer
+        while thread.consumer().slots() < capacity {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // The audio callback asks for fewer samples than a block and runs slower than the render thread
+        const CALLBACK_LEN: usize = 48;
+        let num_callbacks = 100;
+        let callback = std::thread::spawn(move || {
+            let mut samples = Vec::with_capacity(num_callbacks * CALLBACK_LEN);
+            let mut buf = [0.0; CALLBACK_LEN];
+            for _ in 0..num_callbacks {
+                assert_eq!(thread.read_into(&mut buf), CALLBACK_LEN);
+                samples.extend_from_slice(&buf);
+                std::thread::sleep(Duration::from_micros(500));
+            }
+            (thread, samples)
+        });
+        let (mut thread, samples) = callback.join().unwrap();
+
+        assert_eq!(stats.underruns(), 0);
+
+        let num_blocks = samples.len() / NUM_SAMPLES + 1;
+        let expected = render(&mut graph(), num_blocks, false);
+        assert_eq!(samples[..], expected[..samples.len()]);
+
+        // The ring buffer cannot hold more samples than its capacity
+        let mut buf = vec![1.0; capacity + 1];
+        let num_samples = thread.read_into(&mut buf);
+        assert!(num_samples <= capacity);
+        assert!(buf[num_samples..].iter().all(|s| *s == 0.0));
+        assert_eq!(stats.underruns(), 1);
+
+        // The graph is given back once the thread is stopped. The render thread waited for the
+        // callback instead of rendering more than the ring buffer holds
+        let num_read = samples.len() + num_samples;
+        let audio = thread.stop();
+        assert!(audio.get_clock().0 >= num_read);
+        assert!(audio.get_clock().0 <= num_read + capacity);
+    }
+
+    #[test]
+    fn render_thread_dropped_after_panic() {
+        #[derive(Clone)]
+        struct Panic;
+        impl Process<f32> for Panic {
+            fn process_next_value(&mut self, _: &[f32], _: &Context) -> f32 {
+                panic!("the process failed");
+            }
+        }
+
+        let node = Node::<_, _, NUM_SAMPLES>::new("panic", Panic);
+        let thread = Audiograph::new(44100.0, Watcher::on(node)).spawn_render_thread(NUM_SAMPLES);
+        std::thread::sleep(Duration::from_millis(10));
+        // Dropping the handle of the thread does not panic again
+        drop(thread);
+    }
+
+    #[test]
+    fn render_thread_stop_while_full() {
+        let sine = Node::<_, _, NUM_SAMPLES>::new("sine", SineWave::new(0.1, 440.0));
+        let capacity = 4 * NUM_SAMPLES;
+        let mut thread = Audiograph::new(44100.0, Watcher::on(sine)).spawn_render_thread(capacity);
+        let stats = thread.stats();
+
+        while thread.consumer().slots() < capacity || stats.full_waits() == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // The thread waiting for room has not rendered a block ahead of the ring buffer
+        let audio = thread.stop();
+        assert_eq!(audio.get_clock(), SampleIdx(capacity));
+    }
+
+    #[test]
+    fn handle_commands_applied_at_block_start() {
+        let graph = || {
+            let mut mixer = Node::<_, _, NUM_SAMPLES>::new("mixer", Mixer);
+            mixer.add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)));
+            Audiograph::new(44100.0, Watcher::on(mixer))
+        };
+
+        let mut audio = graph();
+        let mut handle = audio.handle(8);
+        assert!(handle.add_input_to("mixer", Node::new("sw2", SineWave::new(0.1, 5000.0))));
+        let event = Event::note_off(Duration::from_millis(10), &handle);
+        assert!(handle.register_event::<SineWave>("sw1", event));
+        assert!(handle.connect("sw2", "mixer") && handle.delete_node("unknown"));
+        // Nothing is applied until a block is streamed
+        assert_eq!(handle.collect_garbage(), 0);
+
+        // The same changes made right before streaming the first block
+        let mut expected = graph();
+        expected.add_input_to("mixer", Node::new("sw2", SineWave::new(0.1, 5000.0)));
+        let event = Event::note_off(Duration::from_millis(10), &expected);
+        expected.register_event::<SineWave>("sw1", event);
+
+        assert_eq!(
+            render(&mut audio, 20, true),
+            render(&mut expected, 20, true)
+        );
+
+        // The deleted node is dropped by the handle
+        assert!(handle.delete_node("sw2"));
+        expected.delete_node("sw2");
+        assert_eq!(render(&mut audio, 5, true), render(&mut expected, 5, true));
+        assert_eq!(handle.collect_garbage(), 1);
+
+        // The queue is full when the graph does not stream
+        while handle.delete_node("unknown") {}
+        render(&mut audio, 1, false);
+        assert!(handle.delete_node("unknown"));
+    }
+
+    // Record the thread a process is dropped on
+    #[derive(Clone)]
+    struct DropThread(std::sync::Arc<std::sync::Mutex<Option<std::thread::ThreadId>>>);
+    impl Process<f32> for DropThread {
+        fn process_next_value(&mut self, _inputs: &[f32], _: &Context) -> f32 {
+            0.0
+        }
+    }
+    impl Drop for DropThread {
+        fn drop(&mut self) {
+            *self.0.lock().unwrap() = Some(std::thread::current().id());
+        }
+    }
+
+    #[test]
+    fn handle_deallocates_off_audio_thread() {
+        let dropped = std::sync::Arc::new(std::sync::Mutex::new(None));
+
+        let mut mixer = Node::<_, _, NUM_SAMPLES>::new("mixer", Mixer);
+        mixer
+            .add_input(Node::new("sw1", SineWave::new(0.1, 2500.0)))
+            .add_input(Node::new("drop", DropThread(dropped.clone())));
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+
+        let mut handle = audio.handle(8);
+        let mut thread = audio.spawn_render_thread(4 * NUM_SAMPLES);
+        assert!(handle.delete_node("drop"));
+
+        // Pull samples until the node is sent back
+        let mut buf = [0.0; NUM_SAMPLES];
+        let mut num_nodes = 0;
+        while num_nodes == 0 {
+            thread.read_into(&mut buf);
+            std::thread::sleep(Duration::from_millis(1));
+            assert!(dropped.lock().unwrap().is_none());
+            num_nodes = handle.collect_garbage();
+        }
+
+        assert_eq!(num_nodes, 1);
+        assert_eq!(*dropped.lock().unwrap(), Some(std::thread::current().id()));
+        thread.stop();
+    }
+
+    #[test]
+    fn handle_rejected_events_deallocated_off_audio_thread() {
+        let mixer = Node::<_, _, NUM_SAMPLES>::new("mixer", Mixer);
+        let mut audio = Audiograph::new(44100.0, Watcher::on(mixer));
+        let mut handle = audio.handle(8);
+        let mut thread = audio.spawn_render_thread(4 * NUM_SAMPLES);
+
+        // The events are rejected as there is no node "unknown" and "mixer" is not a sine wave
+        let dropped = (0..2)
+            .map(|_| std::sync::Arc::new(std::sync::Mutex::new(None)))
+            .collect::<Vec<_>>();
+        for (name, dropped) in ["unknown", "mixer"].into_iter().zip(&dropped) {
+            let node = Node::new("drop", DropThread(dropped.clone()));
+            let event = Event::<_, SineWave, NUM_SAMPLES>::add_input(node, Duration::ZERO, &handle);
+            assert!(handle.register_event(name, event));
+        }
+
+        // Pull samples until the nodes carried by the events are sent back
+        let mut buf = [0.0; NUM_SAMPLES];
+        let mut num_nodes = 0;
+        while num_nodes < 2 {
+            thread.read_into(&mut buf);
+            std::thread::sleep(Duration::from_millis(1));
+            assert!(dropped.iter().all(|d| d.lock().unwrap().is_none()));
+            num_nodes += handle.collect_garbage();
+        }
+
+        assert_eq!(num_nodes, 2);
+        for dropped in dropped.iter() {
+            assert_eq!(*dropped.lock().unwrap(), Some(std::thread::current().id()));
+        }
+        thread.stop();
+    }
+
+    // Process counting its clones, to know when they are dropped
+    #[derive(Clone)]
+    struct Held {
+        _count: std::sync::Arc<()>,
+    }
+    impl Process<f32> for Held {
+        fn process_next_value(&mut self, _: &[f32], _: &Context) -> f32 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn handle_trash_queue_full() {
+        let held = std::sync::Arc::new(());
+        // More nodes than the trash queue holds are removed by a single command
+        let num_held = 8 * NUM_SAMPLES;
+
+        let mut sub = Node::new("sub", Mixer);
+        for _ in 0..num_held {
+            sub.add_input(Node::new(
+                "held",
+                Held {
+                    _count: held.clone(),
+                },
+            ));
+        }
+        let mut mixer = Node::new("mixer", Mixer);
+        mixer.add_input(sub);
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(44100.0, Watcher::on(mixer));
+
+        let mut handle = audio.handle(1);
+        assert!(handle.delete_node("sub"));
+        render(&mut audio, 4, true);
+        // None of the nodes is dropped while rendering
+        assert_eq!(std::sync::Arc::strong_count(&held), num_held + 1);
+
+        // The nodes left over are sent back at the next blocks
+        let mut num_nodes = 0;
+        for _ in 0..16 {
+            let collected = handle.collect_garbage();
+            assert!(collected <= 4 * NUM_SAMPLES);
+            num_nodes += collected;
+            render(&mut audio, 1, true);
+            // Only the collected nodes are dropped, one of them being the mixer "sub"
+            let num_left = std::sync::Arc::strong_count(&held) - 1;
+            assert!(num_held + 1 - num_nodes - num_left <= 1);
+        }
+        assert_eq!(num_nodes, num_held + 1);
+        assert_eq!(std::sync::Arc::strong_count(&held), 1);
+
+        // The commands are applied again once the trash queue has been emptied
+        assert!(handle.add_input_to("mixer", Node::new("sw", SineWave::new(0.5, 440.0))));
+        assert!(render(&mut audio, 1, true).iter().any(|s| *s != 0.0));
+    }
+
+    // Frequency measured from the times the signal goes from negative to positive values.
+    // The time of a crossing is interpolated between the two samples around it
+    fn zero_crossing_freq(samples: &[f32], sample_rate: f32) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| w[0] < 0.0 && w[1] >= 0.0)
+            .map(|(idx, w)| idx as f32 + w[0] / (w[0] - w[1]))
+            .collect::<Vec<_>>();
+
+        let num_periods = (crossings.len() - 1) as f32;
+        num_periods * sample_rate / (crossings[crossings.len() - 1] - crossings[0])
+    }
+
+    #[test]
+    fn sinewave_zero_crossing_frequency() {
+        for sample_rate in [44100.0, 48000.0, 96000.0] {
+            for freq in [20.0, 440.0, 2500.0, 10000.0] {
+                let sw = Node::new("sw", SineWave::new(0.5, freq));
+                let mut audio = Audiograph::<_, NUM_SAMPLES>::new(sample_rate, Watcher::on(sw));
+
+                // One second of sound
+                let num_blocks = sample_rate as usize / NUM_SAMPLES;
+                let samples = render(&mut audio, num_blocks, false);
+
+                let measured = zero_crossing_freq(&samples, sample_rate);
+                assert!((measured - freq).abs() / freq < 1e-3);
+                assert!(samples.iter().all(|s| s.abs() <= 0.5));
+            }
+        }
+    }
+
+    #[test]
+    fn sinewave_phase_continuity() {
+        let sample_rate = 48000.0;
+        let (ampl, freq) = (0.8, 1000.0);
+        let sw = Node::new("sw", SineWave::new(ampl, freq));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(sample_rate, Watcher::on(sw));
+
+        // The frequency changes in the middle of a block
+        let change = 4821;
+        let event = Event::update_params(
+            |f: &mut SineWave| f.params.freq *= 1.1,
+            Duration::from_secs_f64((change as f64 + 0.5) / sample_rate as f64),
+            &audio,
+        );
+        assert!(audio.register_event("sw", event));
+        let samples = render(&mut audio, 2 * sample_rate as usize / NUM_SAMPLES, false);
+
+        // The phase keeps on being accumulated from where it was
+        let mut phase = 0.0f64;
+        for (idx, sample) in samples.iter().enumerate() {
+            let expected = (phase * std::f64::consts::TAU).sin() * ampl as f64;
+            assert!((*sample as f64 - expected).abs() < 1e-5);
+
+            let freq = if idx < change { freq } else { freq * 1.1 };
+            phase += freq as f64 / sample_rate as f64;
+        }
+
+        // No jump: the signal does not move more than the steepest slope of the faster sine
+        let max_step = std::f32::consts::TAU * freq * 1.1 / sample_rate * ampl;
+        assert!(samples
+            .windows(2)
+            .all(|w| (w[1] - w[0]).abs() <= max_step * 1.001));
+
+        // The frequency is the new one after the change
+        let measured = zero_crossing_freq(&samples[change..], sample_rate);
+        assert!((measured - freq * 1.1).abs() / freq < 1e-3);
+    }
+
+    // Record the prepare calls and the contexts given to the process
+    #[derive(Default)]
+    struct ProbeLog {
+        prepared: Vec<(f32, usize)>,
+        contexts: Vec<Context>,
+    }
+    #[derive(Clone)]
+    struct Probe(std::sync::Arc<std::sync::Mutex<ProbeLog>>);
+    impl Process<f32> for Probe {
+        fn process_next_value(&mut self, _: &[f32], ctx: &Context) -> f32 {
+            self.0.lock().unwrap().contexts.push(*ctx);
+            0.0
+        }
+
+        fn prepare(&mut self, sample_rate: f32, max_block: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .prepared
+                .push((sample_rate, max_block));
+        }
+    }
+
+    #[test]
+    fn process_context_and_prepare() {
+        let probe = std::sync::Arc::new(std::sync::Mutex::new(ProbeLog::default()));
+
+        let mixer = Node::new("mixer", Mixer);
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(mixer));
+        audio.set_tempo(90.0);
+        render(&mut audio, 2, false);
+
+        // The node is prepared when it enters the graph
+        assert!(audio.add_input_to("mixer", Node::new("probe", Probe(probe.clone()))));
+        assert_eq!(probe.lock().unwrap().prepared, [(48000.0, NUM_SAMPLES)]);
+        assert!(probe.lock().unwrap().contexts.is_empty());
+
+        render(&mut audio, 2, true);
+        audio.next();
+
+        let contexts = &probe.lock().unwrap().contexts;
+        assert_eq!(contexts.len(), 2 * NUM_SAMPLES + 1);
+        let samples_per_beat = 48000.0 * 60.0 / 90.0;
+        for (idx, ctx) in contexts.iter().enumerate() {
+            let sample = 2 * NUM_SAMPLES + idx;
+            assert_eq!(ctx.sample_rate, 48000.0);
+            assert_eq!(ctx.sample, SampleIdx(sample));
+            assert_eq!(ctx.transport.tempo, 90.0);
+            assert!((ctx.transport.beat - sample as f64 / samples_per_beat).abs() < 1e-9);
+        }
+        // The iterator streams blocks of one sample
+        assert!(contexts[..2 * NUM_SAMPLES]
+            .iter()
+            .all(|ctx| ctx.block_size == NUM_SAMPLES));
+        assert_eq!(contexts[2 * NUM_SAMPLES].block_size, 1);
+    }
+
+    #[test]
+    fn nodes_prepared_before_commands_and_events() {
+        let probe = std::sync::Arc::new(std::sync::Mutex::new(ProbeLog::default()));
+
+        let mixer = Node::new("mixer", Mixer);
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(mixer));
+        let mut handle = audio.handle(8);
+
+        // The node and its inputs are prepared by the handle, not by the graph
+        let mut node = Node::new("probe1", Probe(probe.clone()));
+        node.add_input(Node::new("probe2", Probe(probe.clone())));
+        assert!(handle.add_input_to("mixer", node));
+        assert_eq!(probe.lock().unwrap().prepared.len(), 2);
+        render(&mut audio, 1, true);
+        assert_eq!(probe.lock().unwrap().prepared.len(), 2);
+
+        // A scheduled node is prepared when the event is created
+        let node = Node::new("probe3", Probe(probe.clone()));
+        let event =
+            Event::<_, Mixer, NUM_SAMPLES>::add_input(node, Duration::from_millis(1), &audio);
+        assert_eq!(probe.lock().unwrap().prepared.len(), 3);
+        assert!(audio.register_event("mixer", event));
+        render(&mut audio, 10, true);
+
+        let log = probe.lock().unwrap();
+        assert_eq!(log.prepared, [(48000.0, NUM_SAMPLES); 3]);
+        assert!(!log.contexts.is_empty());
+    }
+
+    // Power of the frequency bins of the samples, from 0 to the Nyquist frequency
+    fn power_spectrum(samples: &[f32]) -> Vec<f64> {
+        let len = samples.len();
+        let twiddles = (0..len)
+            .map(|idx| {
+                let angle = std::f64::consts::TAU * idx as f64 / len as f64;
+                (angle.cos(), angle.sin())
+            })
+            .collect::<Vec<_>>();
+
+        (0..=len / 2)
+            .map(|bin| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (idx, sample) in samples.iter().enumerate() {
+                    let (cos, sin) = twiddles[(bin * idx) % len];
+                    re += *sample as f64 * cos;
+                    im -= *sample as f64 * sin;
+                }
+                re * re + im * im
+            })
+            .collect()
+    }
+
+    // Power of the partials that are not harmonics of the fundamental at `bin`
+    // relative to the power of the harmonics, in dB. The DC is ignored.
+    fn aliasing_db(samples: &[f32], bin: usize) -> f64 {
+        let spectrum = power_spectrum(samples);
+        let harmonics = spectrum.iter().skip(bin).step_by(bin).sum::<f64>();
+        let total = spectrum[1..].iter().sum::<f64>();
+
+        10.0 * ((total - harmonics) / harmonics).log10()
+    }
+
+    #[test]
+    fn oscillators_aliasing() {
+        // The window holds a whole number of cycles so that every partial falls into a bin
+        let sample_rate = 48000.0;
+        let (len, freq) = (4800, 4410.0);
+        let bin = 441;
+        let ctx = Context { sample_rate, ..CTX };
+
+        let square = |t: f64, width: f64| if t < width { 1.0 } else { -1.0 };
+        // The oscillator, its naive waveform and the maximum aliasing allowed in dB
+        type Oscillator = (Box<dyn Process<f32>>, Box<dyn Fn(f64) -> f64>, f64);
+        let oscillators: Vec<Oscillator> = vec![
+            (
+                Box::new(SawWave::new(0.5, freq)),
+                Box::new(|t| 2.0 * t - 1.0),
+                -22.0,
+            ),
+            (
+                Box::new(SquareWave::new(0.5, freq)),
+                Box::new(move |t| square(t, 0.5)),
+                -28.0,
+            ),
+            (
+                Box::new(PulseWave::new(0.5, freq, 0.2)),
+                Box::new(move |t| square(t, 0.2)),
+                -22.0,
+            ),
+            (
+                Box::new(TriangleWave::new(0.5, freq)),
+                Box::new(|t| 4.0 * (t - 0.5).abs() - 1.0),
+                -45.0,
+            ),
+        ];
+
+        for (mut osc, naive, max_aliasing) in oscillators {
+            let samples = (0..len)
+                .map(|_| osc.process_next_value(&[], &ctx))
+                .collect::<Vec<_>>();
+            let naive = (0..len)
+                .map(|idx| naive((idx as f64 * freq as f64 / sample_rate as f64).fract()) as f32)
+                .collect::<Vec<_>>();
+
+            let aliasing = aliasing_db(&samples, bin);
+            assert!(aliasing < max_aliasing);
+            assert!(aliasing < aliasing_db(&naive, bin) - 15.0);
+            assert!(samples.iter().all(|s| s.abs() < 0.5 * 1.1));
+        }
+    }
+
+    #[test]
+    fn pulse_width_update() {
+        let pulse = Node::new("pulse", PulseWave::new(1.0, 480.0, 0.5));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(pulse));
+        let event = Event::update_params(
+            |f: &mut PulseWave| f.params.pulse_width = 0.25,
+            Duration::from_millis(500),
+            &audio,
+        );
+        assert!(audio.register_event("pulse", event));
+
+        // The pulse is up during the given part of each cycle
+        let samples = render(&mut audio, 1500, false);
+        let duty_cycle = |samples: &[f32]| {
+            samples.iter().filter(|s| **s > 0.0).count() as f32 / samples.len() as f32
+        };
+        assert!((duty_cycle(&samples[..24000]) - 0.5).abs() < 0.01);
+        assert!((duty_cycle(&samples[24000..]) - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn pulse_above_nyquist() {
+        // Neither a frequency above the Nyquist frequency nor an invalid width panics
+        for (freq, pulse_width) in [
+            (30000.0, 0.5),
+            (44100.0, 0.1),
+            (-30000.0, 0.9),
+            (1000.0, f32::NAN),
+        ] {
+            let pulse = Node::new("pulse", PulseWave::new(0.5, freq, pulse_width));
+            let mut audio = Audiograph::<_, NUM_SAMPLES>::new(44100.0, Watcher::on(pulse));
+            let samples = render(&mut audio, 100, false);
+            assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+        }
+    }
+
+    // Slope of the power spectrum of the samples between `low` and `high` Hz, in dB per octave
+    fn spectral_slope(samples: &[f32], sample_rate: f32, low: f32, high: f32) -> f64 {
+        // Average the spectrum over several windows to smooth it. The windows are long enough
+        // for a few bins below `low`, and tapered so that the low frequencies do not leak
+        // into the higher bins
+        let len = (4.0 * sample_rate / low).ceil() as usize;
+        let len = len.next_power_of_two().max(512);
+        let hann = (0..len)
+            .map(|idx| {
+                (std::f32::consts::PI * idx as f32 / len as f32)
+                    .sin()
+                    .powi(2)
+            })
+            .collect::<Vec<_>>();
+        let mut spectrum = vec![0.0; len / 2 + 1];
+        for chunk in samples.chunks_exact(len) {
+            let chunk = chunk
+                .iter()
+                .zip(&hann)
+                .map(|(s, w)| s * w)
+                .collect::<Vec<_>>();
+            for (power, p) in spectrum.iter_mut().zip(power_spectrum(&chunk)) {
+                *power += p;
+            }
+        }
+
+        // Least squares fit of the power in dB against the frequency in octaves
+        let points = spectrum
+            .iter()
+            .enumerate()
+            .map(|(bin, power)| (bin as f32 * sample_rate / len as f32, power))
+            .filter(|(freq, _)| *freq >= low && *freq <= high)
+            .map(|(freq, power)| ((freq as f64).log2(), 10.0 * power.log10()))
+            .collect::<Vec<_>>();
+        let num_points = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / num_points;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / num_points;
+        let cov = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum::<f64>();
+        let var = points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+
+        cov / var
+    }
+
+    #[test]
+    fn noise_spectral_slope() {
+        let sample_rate = 44100.0;
+        let num_samples = 64 * 512;
+        let noise = |mut f: Box<dyn Process<f32>>| {
+            (0..num_samples)
+                .map(|_| f.process_next_value(&[], &CTX))
+                .collect::<Vec<_>>()
+        };
+
+        let white = noise(Box::new(WhiteNoise::new(1.0, 1)));
+        let pink = noise(Box::new(PinkNoise::new(1.0, 2)));
+        let brown = noise(Box::new(BrownNoise::new(1.0, 3)));
+
+        let white_slope = spectral_slope(&white, sample_rate, 200.0, 15000.0);
+        let pink_slope = spectral_slope(&pink, sample_rate, 200.0, 15000.0);
+        // The integration of the brown noise only leaks in the lowest octaves
+        let brown_low_slope = spectral_slope(&brown, sample_rate, 50.0, 500.0);
+        let brown_slope = spectral_slope(&brown, sample_rate, 500.0, 5000.0);
+        assert!(white_slope.abs() < 0.5);
+        assert!((pink_slope + 3.0).abs() < 0.5);
+        assert!((brown_low_slope + 6.0).abs() < 0.5);
+        assert!((brown_slope + 6.0).abs() < 0.5);
+
+        // The noises stay around -1..1
+        for samples in [white, pink, brown] {
+            let rms = (samples.iter().map(|s| s * s).sum::<f32>() / num_samples as f32).sqrt();
+            assert!(rms > 0.1 && rms < 0.7);
+            assert!(samples.iter().all(|s| s.abs() < 1.5));
+        }
+    }
+
+    #[test]
+    fn noise_is_reproducible() {
+        let graph = |seed| {
+            let mut mixer = Node::<_, _, NUM_SAMPLES>::new("mixer", Mixer);
+            mixer
+                .add_input(Node::new("white", WhiteNoise::new(0.3, seed)))
+                .add_input(Node::new("pink", PinkNoise::new(0.3, seed + 1)))
+                .add_input(Node::new("brown", BrownNoise::new(0.3, seed + 2)));
+            Audiograph::new(44100.0, Watcher::on(mixer))
+        };
+
+        let samples = render(&mut graph(42), 20, true);
+        assert_eq!(samples, render(&mut graph(42), 20, false));
+        assert_ne!(samples, render(&mut graph(43), 20, false));
+    }
+
+    #[test]
+    fn wavetable_from_harmonics() {
+        let ctx = Context {
+            sample_rate: 48000.0,
+            ..CTX
+        };
+
+        // A single harmonic is a sine
+        let mut wavetable = Wavetable::from_harmonics(0.5, 440.0, &[vec![1.0]]);
+        let mut sine = SineWave::new(0.5, 440.0);
+        for _ in 0..48000 {
+            let value = wavetable.process_next_value(&[], &ctx);
+            assert!((value - sine.process_next_value(&[], &ctx)).abs() < 1e-3);
+        }
+
+        // The sawtooth played at a high pitch keeps only the harmonics below the Nyquist frequency
+        let saw = (1..1024)
+            .map(|harmonic| 2.0 / std::f32::consts::PI / harmonic as f32)
+            .collect::<Vec<_>>();
+        let (len, freq, bin) = (4800, 4410.0, 441);
+        let mut wavetable = Wavetable::from_harmonics(0.5, freq, &[saw]);
+        let samples = (0..len)
+            .map(|_| wavetable.process_next_value(&[], &ctx))
+            .collect::<Vec<_>>();
+        assert!(aliasing_db(&samples, bin) < -60.0);
+        let spectrum = power_spectrum(&samples);
+        assert!(spectrum[4 * bin] > 1e-3 * spectrum[bin]);
+    }
+
+    // Write the samples into a 16 bits mono WAV file in the temporary directory
+    fn write_wav(name: &str, samples: &[f32]) -> std::path::PathBuf {
+        let samples = samples
+            .iter()
+            .map(|s| (s * i16::MAX as f32) as i16)
+            .collect::<Vec<_>>();
+        let mut wav = vec![];
+        let data_len = 2 * samples.len() as u32;
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, 1 channel, 48000 Hz, 96000 bytes per second, 2 bytes per frame, 16 bits
+        for value in [1u16, 1] {
+            wav.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [48000u32, 96000] {
+            wav.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [2u16, 16] {
+            wav.extend_from_slice(&value.to_le_bytes());
+        }
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let path = std::env::temp_dir().join(format!("{}_{}.wav", name, std::process::id()));
+        std::fs::write(&path, wav).unwrap();
+        path
+    }
+
+    #[test]
+    fn wavetable_from_frames() {
+        let ctx = Context {
+            sample_rate: 48000.0,
+            ..CTX
+        };
+        // A cycle of a sine of any length
+        let cycle = |len: usize| {
+            (0..len)
+                .map(|idx| (std::f32::consts::TAU * idx as f32 / len as f32).sin())
+                .collect::<Vec<_>>()
+        };
+
+        for len in [600, 1024, 4096] {
+            let mut wavetable = Wavetable::from_frames(1.0, 1000.0, &[cycle(len)]);
+            let mut sine = SineWave::new(1.0, 1000.0);
+            for _ in 0..4800 {
+                let value = wavetable.process_next_value(&[], &ctx);
+                assert!((value - sine.process_next_value(&[], &ctx)).abs() < 1e-3);
+            }
+        }
+
+        // Two frames, a sine and its opposite, written into a WAV file
+        let frame_len = 256;
+        let samples = cycle(frame_len)
+            .iter()
+            .chain(
+                cycle(frame_len)
+                    .iter()
+                    .map(|s| -s)
+                    .collect::<Vec<_>>()
+                    .iter(),
+            )
+            .copied()
+            .collect::<Vec<_>>();
+        let path = write_wav("wavetable", &samples);
+
+        let wavetable = Wavetable::from_wav(&path, frame_len, 1.0, 1000.0);
+        std::fs::remove_file(&path).unwrap();
+        let mut wavetable = wavetable.unwrap();
+        assert_eq!(wavetable.num_frames(), 2);
+
+        // The last frame is played at the end of the table
+        wavetable.params.position = 1.0;
+        let mut sine = SineWave::new(-1.0, 1000.0);
+        for _ in 0..4800 {
+            let value = wavetable.process_next_value(&[], &ctx);
+            assert!((value - sine.process_next_value(&[], &ctx)).abs() < 1e-3);
+        }
+
+        assert!(Wavetable::from_wav("does_not_exist.wav", 256, 1.0, 1000.0).is_err());
+    }
+
+    #[test]
+    fn wavetable_position_morphing() {
+        // The fundamental only, then its second harmonic only
+        let frames = [vec![1.0], vec![0.0, 1.0]];
+        let wavetable = Node::new("wt", Wavetable::from_harmonics(1.0, 300.0, &frames));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(wavetable));
+        let half = Event::update_params(
+            |f: &mut Wavetable| f.params.position = 0.5,
+            Duration::from_millis(100),
+            &audio,
+        );
+        let last = Event::update_params(
+            |f: &mut Wavetable| f.params.position = 1.0,
+            Duration::from_millis(200),
+            &audio,
+        );
+        assert!(audio.register_event("wt", half) && audio.register_event("wt", last));
+        let samples = render(&mut audio, 500, false);
+
+        let ctx = Context {
+            sample_rate: 48000.0,
+            ..CTX
+        };
+        let mut first = SineWave::new(1.0, 300.0);
+        let mut second = SineWave::new(1.0, 600.0);
+        for (idx, sample) in samples.iter().enumerate() {
+            let (first, second) = (
+                first.process_next_value(&[], &ctx),
+                second.process_next_value(&[], &ctx),
+            );
+            let expected = match idx {
+                0..=4799 => first,
+                4800..=9599 => (first + second) / 2.0,
+                _ => second,
+            };
+            assert!((sample - expected).abs() < 1e-3);
+        }
+    }
+
+    // A constant signal
+    #[derive(Clone)]
+    struct Dc(f32);
+    impl Process<f32> for Dc {
+        fn process_next_value(&mut self, _: &[f32], _: &Context) -> f32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn phase_modulation() {
+        let (fc, fm, index) = (440.0, 110.0, 2.5);
+
+        // DX-style operators: the modulator is an input of the carrier
+        let mut carrier = SineWave::new(0.5, fc);
+        carrier.params.modulation = Modulation::Pm;
+        carrier.params.index = index;
+        let mut carrier = Node::new("carrier", carrier);
+        carrier.add_input(Node::new("modulator", SineWave::new(1.0, fm)));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(carrier));
+        let samples = render(&mut audio, 100, true);
+
+        for (idx, sample) in samples.iter().enumerate() {
+            let t = idx as f64 / 48000.0;
+            let modulator = (std::f64::consts::TAU * fm as f64 * t).sin();
+            let expected =
+                0.5 * (std::f64::consts::TAU * fc as f64 * t + index as f64 * modulator).sin();
+            assert!((*sample as f64 - expected).abs() < 1e-4);
+        }
+
+        // The index can be changed by an event
+        let event = Event::update_params(
+            |f: &mut SineWave| f.params.index = 0.0,
+            Duration::from_secs(1),
+            &audio,
+        );
+        assert!(audio.register_event("carrier", event));
+        render(&mut audio, 1000, false);
+        let samples = render(&mut audio, 100, false);
+        let measured = zero_crossing_freq(&samples, 48000.0);
+        assert!((measured - fc).abs() / fc < 1e-3);
+    }
+
+    #[test]
+    fn frequency_modulation_above_nyquist() {
+        fn check<F: Process<f32> + Clone + Send + Sync + 'static>(
+            oscillator: F,
+            set: fn(&mut F, Modulation),
+        ) {
+            // The modulator pushes the frequency far above the Nyquist frequency and below its opposite
+            for modulation in [Modulation::LinearFm, Modulation::ThroughZeroFm] {
+                for input in [100.0, -100.0, f32::NAN] {
+                    let mut oscillator = oscillator.clone();
+                    set(&mut oscillator, modulation);
+                    let mut node = Node::new("oscillator", oscillator);
+                    node.add_input(Node::new("modulator", Dc(input)));
+                    let mut audio = Audiograph::<_, NUM_SAMPLES>::new(44100.0, Watcher::on(node));
+                    let samples = render(&mut audio, 100, false);
+                    assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+                }
+            }
+        }
+
+        check(SineWave::new(0.5, 1000.0), |f, m| f.params.modulation = m);
+        check(SawWave::new(0.5, 1000.0), |f, m| f.params.modulation = m);
+        check(SquareWave::new(0.5, 1000.0), |f, m| f.params.modulation = m);
+        check(TriangleWave::new(0.5, 1000.0), |f, m| {
+            f.params.modulation = m
+        });
+        check(PulseWave::new(0.5, 1000.0, 0.2), |f, m| {
+            f.params.modulation = m
+        });
+        let harmonics = (1..=1023).map(|h| 1.0 / h as f32).collect::<Vec<_>>();
+        let wavetable = Wavetable::from_harmonics(0.5, 1000.0, &[harmonics]);
+        check(wavetable, |f, m| f.params.modulation = m);
+    }
+
+    #[test]
+    fn wavetable_frequency_modulation_levels() {
+        // The band-limited level is the one of the modulated frequency, not of the carrier
+        let harmonics = (1..=1023).map(|h| 1.0 / h as f32).collect::<Vec<_>>();
+        let mut wavetable = Wavetable::from_harmonics(0.5, 1100.0, &[harmonics]);
+        wavetable.params.modulation = Modulation::LinearFm;
+        let mut node = Node::new("wavetable", wavetable.clone());
+        node.add_input(Node::new("modulator", Dc(3.0)));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(44100.0, Watcher::on(node));
+        let modulated = render(&mut audio, 100, false);
+
+        wavetable.params.freq = 4400.0;
+        wavetable.params.modulation = Modulation::None;
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(
+            44100.0,
+            Watcher::on(Node::new("wavetable", wavetable)),
+        );
+        let expected = render(&mut audio, 100, false);
+
+        for (sample, expected) in modulated.iter().zip(expected) {
+            assert!((sample - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn frequency_modulation() {
+        let freq = 500.0;
+        let graph = |modulation, input| {
+            let mut saw = SawWave::new(0.5, freq);
+            saw.params.modulation = modulation;
+            saw.params.index = 0.5;
+            let mut saw = Node::new("saw", saw);
+            saw.add_input(Node::new("dc", Dc(input)));
+            Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(saw))
+        };
+        let reference = |freq| {
+            let mut saw = Node::new("saw", SawWave::new(0.5, freq));
+            saw.add_input(Node::new("dc", Dc(1.0)));
+            Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(saw))
+        };
+
+        // The frequency is moved by index * freq * input
+        let fm = render(&mut graph(Modulation::LinearFm, 1.0), 100, false);
+        let through_zero = render(&mut graph(Modulation::ThroughZeroFm, 1.0), 100, false);
+        let expected = render(&mut reference(freq * 1.5), 100, false);
+        assert_eq!(fm, expected);
+        assert_eq!(through_zero, expected);
+
+        // Linear FM stops at 0 Hz
+        let fm = render(&mut graph(Modulation::LinearFm, -4.0), 100, false);
+        assert!(fm.iter().all(|s| *s == fm[0]));
+
+        // Through-zero FM runs backward: the sawtooth goes down
+        let through_zero = render(&mut graph(Modulation::ThroughZeroFm, -4.0), 100, false);
+        let expected = render(&mut reference(freq), 100, false);
+        for (sample, expected) in through_zero.iter().zip(expected) {
+            assert!((sample + expected).abs() < 1e-4);
+        }
+
+        // The inputs are ignored without modulation
+        let none = render(&mut graph(Modulation::None, 1.0), 100, false);
+        assert_eq!(none, render(&mut reference(freq), 100, false));
+    }
+
+    // A single sample of 1 at the given sample index, zero elsewhere
+    #[derive(Clone)]
+    struct Impulse(usize);
+    impl Process<f32> for Impulse {
+        fn process_next_value(&mut self, _: &[f32], ctx: &Context) -> f32 {
+            if ctx.sample.0 == self.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+
+    // Magnitude of the spectrum of `samples` at the frequency `freq`
+    fn magnitude_at(samples: &[f32], freq: f64, sample_rate: f64) -> f64 {
+        let w = std::f64::consts::TAU * freq / sample_rate;
+        let (re, im) = samples
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, s)| {
+                let (sin, cos) = (w * n as f64).sin_cos();
+                (re + *s as f64 * cos, im - *s as f64 * sin)
+            });
+        (re * re + im * im).sqrt()
+    }
+
+    #[test]
+    fn biquad_magnitude_response() {
+        let sample_rate = 48000.0;
+        let (cutoff, q, gain_db) = (1000.0, 2.0, 6.0);
+        let a = 10f64.powf(gain_db / 40.0);
+        let sqrt_a = a.sqrt();
+
+        // The analog prototypes of the cookbook as the coefficients of s^2, s and 1
+        // of their numerator and denominator, and their gain
+        let prototypes = [
+            (
+                BiquadKind::LowPass,
+                [0.0, 0.0, 1.0],
+                [1.0, 1.0 / q, 1.0],
+                1.0,
+            ),
+            (
+                BiquadKind::HighPass,
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0 / q, 1.0],
+                1.0,
+            ),
+            (
+                BiquadKind::BandPass,
+                [0.0, 1.0 / q, 0.0],
+                [1.0, 1.0 / q, 1.0],
+                1.0,
+            ),
+            (BiquadKind::Notch, [1.0, 0.0, 1.0], [1.0, 1.0 / q, 1.0], 1.0),
+            (
+                BiquadKind::Peaking,
+                [1.0, a / q, 1.0],
+                [1.0, 1.0 / (a * q), 1.0],
+                1.0,
+            ),
+            (
+                BiquadKind::LowShelf,
+                [1.0, sqrt_a / q, a],
+                [a, sqrt_a / q, 1.0],
+                a,
+            ),
+            (
+                BiquadKind::HighShelf,
+                [a, sqrt_a / q, 1.0],
+                [1.0, sqrt_a / q, a],
+                a,
+            ),
+        ];
+
+        for (kind, num, den, gain) in prototypes {
+            let filter = Biquad::new(kind, cutoff as f32, q as f32, gain_db as f32);
+            let (_, samples) =
+                render_process(filter, vec![Box::new(Impulse(0))], sample_rate as f32, 128);
+
+            for freq in [
+                20.0, 100.0, 500.0, 900.0, 1000.0, 1100.0, 2000.0, 8000.0, 20000.0,
+            ] {
+                // The bilinear transform maps the frequency to the analog one
+                let w = (std::f64::consts::PI * freq / sample_rate).tan()
+                    / (std::f64::consts::PI * cutoff / sample_rate).tan();
+                let eval = |p: [f64; 3]| (p[2] - p[0] * w * w).hypot(p[1] * w);
+                let expected = gain * eval(num) / eval(den);
+
+                let measured = magnitude_at(&samples, freq, sample_rate);
+                assert!(
+                    (measured - expected).abs() < 1e-3 * expected.max(1.0),
+                    "{:?} at {} Hz: {} instead of {}",
+                    kind,
+                    freq,
+                    measured,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn biquad_parameters_update() {
+        let sample_rate = 48000.0;
+        let at = 48000 + 100;
+        let filter = Biquad::new(BiquadKind::LowPass, 200.0, 0.7, 0.0);
+        let (mut audio, _) = render_process(filter, vec![Box::new(Impulse(at))], sample_rate, 0);
+
+        let event = Event::update_params(
+            |f: &mut Biquad| f.params.cutoff = 5000.0,
+            Duration::from_secs(1),
+            &audio,
+        );
+        assert!(audio.register_event("node", event));
+        let samples = render(&mut audio, 1000, false);
+
+        // The coefficients follow the new cutoff
+        let filter = Biquad::new(BiquadKind::LowPass, 5000.0, 0.7, 0.0);
+        let (_, expected) = render_process(filter, vec![Box::new(Impulse(0))], sample_rate, 1000);
+        assert_eq!(samples[at..], expected[..samples.len() - at]);
+    }
+
+    #[test]
+    fn state_variable_filter_responses() {
+        // Without resonance the input is low + 2 * band + high
+        let mut svf = StateVariableFilter::new(1000.0, 0.0);
+        let mut noise = WhiteNoise::new(0.5, 3);
+        for _ in 0..10000 {
+            let input = noise.process_next_value(&[], &CTX);
+            let out = svf.tick(input, 1000.0, 48000.0);
+            assert!((out.low + 2.0 * out.band + out.high - input).abs() < 1e-5);
+        }
+
+        // A constant goes through the low-pass only
+        let mut svf = StateVariableFilter::new(1000.0, 0.5);
+        let mut out = svf.tick(1.0, 1000.0, 48000.0);
+        for _ in 0..10000 {
+            out = svf.tick(1.0, 1000.0, 48000.0);
+        }
+        assert!((out.low - 1.0).abs() < 1e-4);
+        assert!(out.band.abs() < 1e-4 && out.high.abs() < 1e-4);
+    }
+
+    #[test]
+    fn filters_cutoff_modulation() {
+        // A constant modulation of 1 doubles the cutoff
+        let modulated = render_process(
+            StateVariableFilter::new(500.0, 0.5),
+            vec![Box::new(WhiteNoise::new(0.5, 7)), Box::new(Dc(1.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        let expected = render_process(
+            StateVariableFilter::new(1000.0, 0.5),
+            vec![Box::new(WhiteNoise::new(0.5, 7))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert_eq!(modulated, expected);
+
+        let modulated = render_process(
+            LadderFilter::new(500.0, 0.5),
+            vec![Box::new(WhiteNoise::new(0.5, 7)), Box::new(Dc(1.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        let expected = render_process(
+            LadderFilter::new(1000.0, 0.5),
+            vec![Box::new(WhiteNoise::new(0.5, 7))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert_eq!(modulated, expected);
+    }
+
+    #[test]
+    fn filters_stable_at_max_resonance() {
+        let svf = render_process(
+            StateVariableFilter::new(1000.0, 1.0),
+            vec![Box::new(WhiteNoise::new(0.5, 7))],
+            48000.0,
+            1500,
+        )
+        .1;
+        let ladder = render_process(
+            LadderFilter::new(1000.0, 1.0),
+            vec![Box::new(WhiteNoise::new(0.5, 7))],
+            48000.0,
+            1500,
+        )
+        .1;
+        for samples in [svf, ladder] {
+            assert!(samples.iter().all(|s| s.is_finite() && s.abs() < 20.0));
+        }
+
+        // The ladder self-oscillates after an impulse, without growing
+        let (_, samples) = render_process(
+            LadderFilter::new(1000.0, 1.0),
+            vec![Box::new(Impulse(0))],
+            48000.0,
+            1500,
+        );
+        let last = &samples[samples.len() - 4800..];
+        let peak = last.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak > 0.1 && peak < 2.0);
+
+        // The state variable filter rings down
+        let (_, samples) = render_process(
+            StateVariableFilter::new(1000.0, 1.0),
+            vec![Box::new(Impulse(0))],
+            48000.0,
+            1500,
+        );
+        assert!(samples.iter().all(|s| s.is_finite() && s.abs() < 1.0));
+        assert!(samples[samples.len() - 4800..]
+            .iter()
+            .all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn filters_stable_under_fast_modulation() {
+        // The cutoff is swept over 8 octaves at an audio rate
+        let lfo = SineWave::new(1.0, 5000.0);
+        let mut svf = StateVariableFilter::new(1000.0, 0.95);
+        svf.params.depth = 4.0;
+        svf.params.band = 1.0;
+        svf.params.high = 1.0;
+        let mut ladder = LadderFilter::new(1000.0, 0.95);
+        ladder.params.depth = 4.0;
+
+        let svf = render_process(
+            svf,
+            vec![Box::new(WhiteNoise::new(0.5, 7)), Box::new(lfo.clone())],
+            48000.0,
+            1500,
+        )
+        .1;
+        let ladder = render_process(
+            ladder,
+            vec![Box::new(WhiteNoise::new(0.5, 7)), Box::new(lfo)],
+            48000.0,
+            1500,
+        )
+        .1;
+        for samples in [svf, ladder] {
+            assert!(samples.iter().all(|s| s.is_finite() && s.abs() < 20.0));
+        }
+    }
+
+    // Render the envelope of a note played from 0.1 s to 0.5 s, and the samples of the note on and off
+    fn render_note(adsr: Adsr, input: Option<Dc>) -> (Vec<f32>, usize, usize) {
+        let inputs = input.map_or(vec![], |input| {
+            vec![Box::new(input) as Box<dyn Process<f32>>]
+        });
+        let (mut audio, _) = render_process(adsr, inputs, 48000.0, 0);
+
+        let (on, off) = (Duration::from_millis(100), Duration::from_millis(500));
+        let event = Event::<_, Adsr, NUM_SAMPLES>::note_on(on, &audio);
+        assert!(audio.register_event("node", event));
+        let event = Event::<_, Adsr, NUM_SAMPLES>::note_off(off, &audio);
+        assert!(audio.register_event("node", event));
+
+        let (on, off) = (audio.sample_idx_at(on).0, audio.sample_idx_at(off).0);
+        (render(&mut audio, 500, false), on, off)
+    }
+
+    #[test]
+    fn adsr_linear() {
+        // 480 samples of attack, 960 of decay and 2400 of release
+        let (samples, on, off) = render_note(Adsr::new(0.01, 0.02, 0.5, 0.05), None);
+        let near = |idx: usize, expected: f32| (samples[idx] - expected).abs() < 1e-4;
+
+        assert!(samples[..on].iter().all(|s| *s == 0.0));
+        assert!(near(on + 239, 0.5));
+        assert!(near(on + 479, 1.0));
+        assert!(near(on + 480 + 479, 0.75));
+        assert!(near(on + 480 + 959, 0.5));
+        assert!(samples[on + 1440..off].iter().all(|s| *s == 0.5));
+
+        // The release rings out after the note off
+        assert!(near(off, 0.5 - 0.5 / 2400.0));
+        assert!(near(off + 1199, 0.25));
+        assert!(near(off + 2399, 0.0));
+        assert!(samples[off + 2400..].iter().all(|s| *s == 0.0));
+
+        // With an input the envelope is applied to it
+        let (gated, _, _) = render_note(Adsr::new(0.01, 0.02, 0.5, 0.05), Some(Dc(2.0)));
+        for (gated, envelope) in gated.iter().zip(samples) {
+            assert_eq!(*gated, 2.0 * envelope);
+        }
+    }
+
+    #[test]
+    fn adsr_exponential() {
+        let mut adsr = Adsr::new(0.01, 0.02, 0.5, 0.05);
+        adsr.params.curve = AdsrCurve::Exponential;
+        let (samples, on, off) = render_note(adsr, None);
+        let near = |idx: usize, expected: f32| (samples[idx] - expected).abs() < 1e-3;
+
+        // The stages end in time
+        assert!(near(on + 479, 1.0));
+        assert!(near(on + 480 + 959, 0.5));
+        assert!(samples[on + 1440..off].iter().all(|s| *s == 0.5));
+        assert!(near(off + 2399, 0.0));
+        assert!(samples[off + 2400..].iter().all(|s| *s == 0.0));
+
+        // The curves are faster at the start of the stages
+        assert!(samples[on + 239] > 0.55);
+        assert!(samples[on + 480 + 479] < 0.7);
+        assert!(samples[off + 1199] < 0.2);
+    }
+
+    #[test]
+    fn adsr_retrigger() {
+        let adsr = Node::new("adsr", Adsr::new(0.01, 0.02, 0.5, 0.05));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(adsr));
+        for (time, on) in [(0, true), (100, false), (110, true)] {
+            let time = Duration::from_millis(time);
+            let event = if on {
+                Event::<_, Adsr, NUM_SAMPLES>::note_on(time, &audio)
+            } else {
+                Event::<_, Adsr, NUM_SAMPLES>::note_off(time, &audio)
+            };
+            assert!(audio.register_event("adsr", event));
+        }
+        let samples = render(&mut audio, 200, false);
+
+        // A note on during the release starts the attack from the current level
+        let retrigger = audio.sample_idx_at(Duration::from_millis(110)).0;
+        assert!(samples[retrigger - 1] > 0.3);
+        assert!(samples
+            .windows(2)
+            .all(|w| (w[1] - w[0]).abs() < 2.0 / 480.0));
+    }
+
+    // Render a constant ramped from 1 to `target`, and the samples of the start and the end of the ramp
+    fn render_ramp(target: f32, curve: RampCurve) -> (Vec<f32>, usize, usize) {
+        let (mut audio, _) = render_process(Dc(1.0), vec![], 48000.0, 0);
+
+        let (start, duration) = (Duration::from_millis(10), Duration::from_millis(20));
+        let ramp = Event::ramp_to(
+            |f: &mut Dc| &mut f.0,
+            target,
+            start,
+            duration,
+            curve,
+            &audio,
+        );
+        assert!(audio.register_event("node", ramp));
+
+        let start_idx = audio.sample_idx_at(start).0;
+        let end_idx = audio.sample_idx_at(start + duration).0;
+        (render(&mut audio, 100, false), start_idx, end_idx)
+    }
+
+    #[test]
+    fn ramp_curves() {
+        let midpoints = [
+            (RampCurve::Linear, 2.0),
+            (RampCurve::Exponential, 3f32.sqrt()),
+            (RampCurve::SCurve, 2.0),
+        ];
+
+        for (curve, midpoint) in midpoints {
+            let (samples, start, end) = render_ramp(3.0, curve);
+            assert_eq!((end - start) % 2, 0);
+
+            assert!(samples[..=start].iter().all(|s| *s == 1.0));
+            assert!((samples[(start + end) / 2] - midpoint).abs() < 1e-5);
+            assert!(samples[end..].iter().all(|s| *s == 3.0));
+            assert!(samples[start..=end].windows(2).all(|w| w[1] > w[0]));
+        }
+
+        // The S-curve starts slower than the linear ramp
+        let (linear, start, end) = render_ramp(3.0, RampCurve::Linear);
+        let (s_curve, _, _) = render_ramp(3.0, RampCurve::SCurve);
+        let quarter = start + (end - start) / 4;
+        assert!((s_curve[quarter] - 1.3125).abs() < 1e-5);
+        assert!(s_curve[quarter] < linear[quarter]);
+
+        // An exponential ramp through 0 falls back to a linear one
+        let (exponential, _, _) = render_ramp(-1.0, RampCurve::Exponential);
+        let (linear, _, _) = render_ramp(-1.0, RampCurve::Linear);
+        assert_eq!(exponential, linear);
+    }
+
+    #[test]
+    fn ramp_replaces_ramp() {
+        let dc = Node::new("dc", Dc(0.0));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(dc));
+
+        // The second ramp starts from the value reached by the first one
+        let ramps = [(0.0, 100.0, 1.0), (0.5, 0.5, -1.0)];
+        for (start, duration, target) in ramps {
+            let ramp = Event::ramp_to(
+                |f: &mut Dc| &mut f.0,
+                target,
+                Duration::from_secs_f32(start),
+                Duration::from_secs_f32(duration),
+                RampCurve::Linear,
+                &audio,
+            );
+            assert!(audio.register_event("dc", ramp));
+        }
+        let samples = render(&mut audio, 2000, false);
+
+        let start = audio.sample_idx_at(Duration::from_secs_f32(0.5)).0;
+        assert!(samples[start] > 0.0 && samples[start] < 0.01);
+        assert!(samples[start..].windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(*samples.last().unwrap(), -1.0);
+    }
+
+    #[test]
+    fn delay_impulse_response() {
+        let interpolations = [
+            DelayInterpolation::Linear,
+            DelayInterpolation::Hermite,
+            DelayInterpolation::Allpass,
+        ];
+
+        for interpolation in interpolations {
+            // Echoes every 480 samples, decaying by the feedback
+            let mut delay = Delay::new(0.01, 0.1);
+            delay.params.feedback = 0.5;
+            delay.params.interpolation = interpolation;
+            let (_, samples) = render_process(delay, vec![Box::new(Impulse(0))], 48000.0, 100);
+
+            for (idx, sample) in samples.iter().enumerate() {
+                let expected = if idx > 0 && idx % 480 == 0 {
+                    0.5f32.powi(idx as i32 / 480 - 1)
+                } else {
+                    0.0
+                };
+                assert!((sample - expected).abs() < 1e-4);
+            }
+
+            // Half a sample is between two samples, the low frequencies being delayed by 480.5 samples
+            let mut delay = Delay::new(480.5 / 48000.0, 0.1);
+            delay.params.interpolation = interpolation;
+            let (_, samples) = render_process(delay, vec![Box::new(Impulse(0))], 48000.0, 100);
+            let sum = samples.iter().sum::<f32>();
+            let mean = samples
+                .iter()
+                .enumerate()
+                .map(|(idx, s)| idx as f32 * s)
+                .sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-3);
+            assert!((mean / sum - 480.5).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn delay_feedback_bounded() {
+        // A feedback above 1 is limited, the echoes decay instead of growing without bound
+        let mut delay = Delay::new(0.01, 0.1);
+        delay.params.feedback = 1.5;
+        let (_, samples) = render_process(delay, vec![Box::new(Impulse(0))], 48000.0, 300);
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+        let last_echo = samples.len() / 480 * 480 - 480;
+        assert!((samples[last_echo] - 0.99f32.powi(last_echo as i32 / 480 - 1)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn delay_mix_and_beats() {
+        // Half of the input and half of the signal delayed by a quarter of a beat
+        let mut delay = Delay::new(0.0, 1.0);
+        delay.params.beats = Some(0.25);
+        delay.params.mix = 0.5;
+        let (_, samples) = render_process(delay, vec![Box::new(Impulse(0))], 48000.0, 200);
+
+        // A beat lasts 24000 samples at 120 bpm
+        let delay = 6000;
+        for (idx, sample) in samples.iter().enumerate() {
+            let expected = if idx == 0 || idx == delay { 0.5 } else { 0.0 };
+            assert!((sample - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn delay_time_change_glides() {
+        let mut delay = Node::new("delay", Delay::new(0.01, 0.1));
+        delay.add_input(Node::new("sine", SineWave::new(0.5, 440.0)));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(delay));
+        let event = Event::update_params(
+            |f: &mut Delay| f.params.time = 0.02,
+            Duration::from_millis(500),
+            &audio,
+        );
+        assert!(audio.register_event("delay", event));
+        let samples = render(&mut audio, 1500, false);
+
+        // The output does not jump, it is a sine slightly shifted in pitch during the glide
+        let max_step = 0.5 * std::f32::consts::TAU * 440.0 / 48000.0;
+        assert!(samples
+            .windows(2)
+            .all(|w| (w[1] - w[0]).abs() < 1.3 * max_step));
+
+        // Then the delay is the new one
+        let sine = render(
+            &mut Audiograph::<_, NUM_SAMPLES>::new(
+                48000.0,
+                Watcher::on(Node::new("sine", SineWave::new(0.5, 440.0))),
+            ),
+            1500,
+            false,
+        );
+        for idx in samples.len() - 1000..samples.len() {
+            assert!((samples[idx] - sine[idx - 960]).abs() < 1e-3);
+        }
+    }
+
+    // Time for the energy of an impulse response to decay by 60 dB, extrapolated
+    // from its decay between -5 and -35 dB by Schroeder's backward integration
+    fn measure_rt60(samples: &[f32], sample_rate: f32) -> f32 {
+        let mut energy = samples
+            .iter()
+            .rev()
+            .scan(0.0, |energy, s| {
+                *energy += (*s as f64).powi(2);
+                Some(*energy)
+            })
+            .collect::<Vec<_>>();
+        energy.reverse();
+
+        let time_at = |db: f64| {
+            let idx = energy
+                .iter()
+                .position(|e| 10.0 * (e / energy[0]).log10() < db)
+                .unwrap();
+            idx as f32 / sample_rate
+        };
+        2.0 * (time_at(-35.0) - time_at(-5.0))
+    }
+
+    #[test]
+    fn reverb_decay() {
+        for room_size in [0.3, 0.6, 0.9] {
+            let mut reverb = Reverb::new(room_size, 0.0);
+            reverb.params.mix = 1.0;
+            let expected = reverb.rt60();
+            let (_, samples) = render_process(reverb, vec![Box::new(Impulse(0))], 48000.0, 6000);
+
+            let measured = measure_rt60(&samples, 48000.0);
+            assert!((measured - expected).abs() / expected < 0.2);
+        }
+
+        // The damping shortens the decay
+        let mut reverb = Reverb::new(0.6, 0.0);
+        reverb.params.mix = 1.0;
+        let (_, undamped) =
+            render_process(reverb.clone(), vec![Box::new(Impulse(0))], 48000.0, 3000);
+        reverb.params.damping = 1.0;
+        let (_, damped) = render_process(reverb, vec![Box::new(Impulse(0))], 48000.0, 3000);
+        assert!(measure_rt60(&damped, 48000.0) < measure_rt60(&undamped, 48000.0));
+    }
+
+    #[test]
+    fn reverb_pre_delay_and_mix() {
+        let mut reverb = Reverb::new(0.5, 0.5);
+        reverb.params.pre_delay = 0.1;
+        reverb.params.mix = 0.25;
+        let (_, samples) = render_process(reverb, vec![Box::new(Impulse(0))], 48000.0, 400);
+
+        // Only the dry impulse plays before the pre-delay and the shortest comb
+        assert_eq!(samples[0], 0.75);
+        let first = 4800 + 1116 * 48000 / 44100;
+        assert!(samples[1..first].iter().all(|s| *s == 0.0));
+        assert!(samples[first] != 0.0);
+        assert!(samples.iter().all(|s| s.is_finite() && s.abs() < 1.0));
+    }
+
+    #[test]
+    fn convolver_matches_direct_convolution() {
+        // A decaying noise whose length is not a multiple of the block size
+        let mut noise = WhiteNoise::new(1.0, 11);
+        let ir = (0..1000)
+            .map(|idx| noise.process_next_value(&[], &CTX) * (-(idx as f32) / 200.0).exp())
+            .collect::<Vec<_>>();
+        let samples = render_process(
+            Convolver::new(ir.clone()),
+            vec![Box::new(WhiteNoise::new(0.5, 7))],
+            48000.0,
+            100,
+        )
+        .1;
+
+        // The same noise as the input of the convolver
+        let mut noise = WhiteNoise::new(0.5, 7);
+        let input = (0..samples.len())
+            .map(|_| noise.process_next_value(&[], &CTX))
+            .collect::<Vec<_>>();
+
+        // The convolution is late by a block
+        for (idx, sample) in samples.iter().enumerate() {
+            let expected = (0..ir.len())
+                .filter(|m| idx >= NUM_SAMPLES + m)
+                .map(|m| ir[m] * input[idx - NUM_SAMPLES - m])
+                .sum::<f32>();
+            assert!((sample - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn convolver_latency_and_mix() {
+        let mut convolver = Convolver::new(vec![0.5, 0.25]);
+        assert_eq!(convolver.latency(), None);
+        // Like the other buffered processes, the input is passed through until it is prepared
+        assert_eq!(convolver.process_next_value(&[0.3], &CTX), 0.3);
+        convolver.prepare(48000.0, NUM_SAMPLES);
+        assert_eq!(convolver.latency(), Some(NUM_SAMPLES));
+
+        // The dry signal is delayed as well to stay in time with the convolved one
+        convolver.params.mix = 0.5;
+        let (_, samples) = render_process(convolver, vec![Box::new(Impulse(0))], 48000.0, 4);
+        for (idx, sample) in samples.iter().enumerate() {
+            let expected = match idx {
+                NUM_SAMPLES => 0.5 + 0.5 * 0.5,
+                idx if idx == NUM_SAMPLES + 1 => 0.5 * 0.25,
+                _ => 0.0,
+            };
+            assert!((sample - expected).abs() < 1e-6);
+        }
+
+        // The impulse response can be read from a WAV file
+        let path = write_wav("convolver", &[0.5, 0.0, -0.25]);
+        let convolver = Convolver::from_wav(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (_, samples) =
+            render_process(convolver.unwrap(), vec![Box::new(Impulse(0))], 48000.0, 4);
+        assert!((samples[NUM_SAMPLES] - 0.5).abs() < 1e-3);
+        assert!((samples[NUM_SAMPLES + 2] + 0.25).abs() < 1e-3);
+
+        assert!(Convolver::from_wav("does_not_exist.wav").is_err());
+    }
+
+    fn db(value: f32) -> f32 {
+        20.0 * value.abs().log10()
+    }
+
+    fn from_db(db: f32) -> f32 {
+        10f32.powf(db / 20.0)
+    }
+
+    #[test]
+    fn compressor_static_curve() {
+        let (threshold, ratio, knee, makeup) = (-20.0, 4.0, 6.0, 3.0);
+        // The output level of the textbook soft knee compressor
+        let curve = |level: f32| {
+            if level < threshold - knee / 2.0 {
+                level
+            } else if level > threshold + knee / 2.0 {
+                threshold + (level - threshold) / ratio
+            } else {
+                level
+                    + (1.0 / ratio - 1.0) * (level - threshold + knee / 2.0).powi(2) / (2.0 * knee)
+            }
+        };
+
+        for level in [-40.0, -23.0, -21.0, -20.0, -18.0, -10.0, 0.0] {
+            let mut compressor = Compressor::new(threshold, ratio);
+            compressor.params.knee = knee;
+            compressor.params.makeup = makeup;
+            let samples =
+                render_process(compressor, vec![Box::new(Dc(from_db(level)))], 48000.0, 200).1;
+
+            let expected = curve(level) + makeup;
+            assert!((db(*samples.last().unwrap()) - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn compressor_attack_and_sidechain() {
+        // The gain reduction of 15 dB is reached exponentially, in the attack time constant
+        let mut compressor = Compressor::new(-20.0, 4.0);
+        compressor.params.attack = 0.01;
+        let samples = render_process(compressor.clone(), vec![Box::new(Dc(1.0))], 48000.0, 100).1;
+        let expected = -15.0 * (1.0 - (-1.0f32).exp());
+        assert!((db(samples[480]) - expected).abs() < 0.05);
+        assert!((db(*samples.last().unwrap()) + 15.0).abs() < 0.01);
+
+        // The level is read from the sidechain
+        let samples = render_process(
+            compressor,
+            vec![Box::new(Dc(0.1)), Box::new(Dc(1.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert!((db(*samples.last().unwrap()) - (-20.0 - 15.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn expander_and_gate_static_curves() {
+        let (threshold, ratio, range) = (-40.0, 2.0, 30.0);
+        for (level, expected) in [
+            (-20.0, -20.0),
+            (-40.0, -40.0),
+            (-50.0, -60.0),
+            (-80.0, -110.0),
+        ] {
+            let mut expander = Expander::new(threshold, ratio);
+            expander.params.range = range;
+            let samples =
+                render_process(expander, vec![Box::new(Dc(from_db(level)))], 48000.0, 1000).1;
+            assert!((db(*samples.last().unwrap()) - expected).abs() < 0.01);
+        }
+
+        // In the knee the gain is between the ones of the two sides
+        let mut expander = Expander::new(threshold, ratio);
+        expander.params.knee = 4.0;
+        let samples = render_process(expander, vec![Box::new(Dc(from_db(-40.0)))], 48000.0, 1000).1;
+        assert!((db(*samples.last().unwrap()) - (-40.0 - 0.5)).abs() < 0.01);
+
+        for (level, expected) in [(-30.0, -30.0), (-40.0, -40.0), (-50.0, -130.0)] {
+            let samples = render_process(
+                Gate::new(threshold),
+                vec![Box::new(Dc(from_db(level)))],
+                48000.0,
+                1000,
+            )
+            .1;
+            assert!((db(*samples.last().unwrap()) - expected).abs() < 0.01);
+        }
+        let mut gate = Gate::new(threshold);
+        gate.params.knee = 10.0;
+        let samples = render_process(gate, vec![Box::new(Dc(from_db(-40.0)))], 48000.0, 1000).1;
+        assert!((db(*samples.last().unwrap()) - (-40.0 - 40.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn gate_opening_and_closing_times() {
+        // The sidechain closes the gate, opens it at 500 ms and lets it close at 700 ms
+        let mut node = Node::new("gate", Gate::new(-40.0));
+        node.add_input(Node::new("input", Dc(1.0)));
+        node.add_input(Node::new("sidechain", Dc(from_db(-80.0))));
+        let mut audio = Audiograph::<_, NUM_SAMPLES>::new(48000.0, Watcher::on(node));
+        let open =
+            Event::update_params(|dc: &mut Dc| dc.0 = 1.0, Duration::from_millis(500), &audio);
+        let close = Event::update_params(
+            |dc: &mut Dc| dc.0 = from_db(-80.0),
+            Duration::from_millis(700),
+            &audio,
+        );
+        assert!(audio.register_event("sidechain", open));
+        assert!(audio.register_event("sidechain", close));
+        let samples = render(&mut audio, 48000 / NUM_SAMPLES, false);
+
+        // The gain moves by the range of 80 dB exponentially, in the attack time constant
+        // when the gate opens and in the release time constant when it closes
+        let (attack, release) = (0.001 * 48000.0, 0.05 * 48000.0);
+        let opened = samples[24000..]
+            .iter()
+            .position(|s| db(*s) > -80.0 * (-1.0f32).exp())
+            .unwrap();
+        assert!((opened as f32 + 1.0 - attack).abs() <= 1.0);
+        let closed = samples[33600..]
+            .iter()
+            .position(|s| db(*s) < -80.0 * (1.0 - (-1.0f32).exp()))
+            .unwrap();
+        assert!((closed as f32 + 1.0 - release).abs() <= 1.0);
+    }
+
+    #[test]
+    fn limiter_lookahead() {
+        let mut limiter = Limiter::new(-6.0, 0.005);
+        limiter.prepare(48000.0, NUM_SAMPLES);
+        let latency = limiter.latency().unwrap();
+        assert_eq!(latency, 240);
+
+        let graph = |ampl: f32| {
+            let sine = || vec![Box::new(SineWave::new(ampl, 1000.0)) as Box<dyn Process<f32>>];
+            let (_, limited) = render_process(Limiter::new(-6.0, 0.005), sine(), 48000.0, 100);
+            let (_, sine) = render_process(Mixer, sine(), 48000.0, 100);
+            (limited, sine)
+        };
+
+        // No sample goes above the threshold, even the first peak, up to the rounding of the smoothing
+        let (limited, sine) = graph(1.0);
+        assert!(limited
+            .iter()
+            .all(|s| s.abs() <= from_db(-6.0) * (1.0 + 1e-5)));
+        assert!(limited[latency..].iter().any(|s| s.abs() > from_db(-6.5)));
+        assert!(sine.iter().any(|s| s.abs() > 0.99));
+
+        // Below the threshold the input is only delayed
+        let (limited, sine) = graph(0.25);
+        assert!(limited[..latency].iter().all(|s| *s == 0.0));
+        for (limited, sine) in limited[latency..].iter().zip(sine) {
+            assert!((limited - sine).abs() < 1e-6);
+        }
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn phaser_notch() {
+        // Without sweep, the six stages shift a sine at their frequency by 540 degrees
+        let mut phaser = Phaser::new(1.0, 0.0);
+        phaser.params.freq = 1000.0;
+        let processed = render_process(
+            phaser.clone(),
+            vec![Box::new(SineWave::new(0.5, 1000.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert!(peak(&processed[4800..]) < 0.005);
+
+        // and barely touch the low frequencies
+        let processed = render_process(
+            phaser.clone(),
+            vec![Box::new(SineWave::new(0.5, 50.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert!(peak(&processed[4800..]) > 0.45);
+
+        // The notch moves with the LFO
+        phaser.params.depth = 1.0;
+        let processed = render_process(
+            phaser,
+            vec![Box::new(SineWave::new(0.5, 1000.0))],
+            48000.0,
+            100,
+        )
+        .1;
+        assert!(peak(&processed[4800..]) > 0.1);
+    }
+
+    #[test]
+    fn flanger_and_chorus_delays() {
+        // Without sweep, the flanger is a comb of 0.1 ms, 4.8 samples
+        let mut flanger = Flanger::new(1.0, 0.0);
+        flanger.params.feedback = 0.0;
+        let (_, samples) = render_process(flanger, vec![Box::new(Impulse(0))], 48000.0, 10);
+        assert!((samples[0] - 0.5).abs() < 1e-6);
+        assert!((samples[4] - 0.1).abs() < 1e-4);
+        assert!((samples[5] - 0.4).abs() < 1e-4);
+        assert_eq!(samples.iter().filter(|s| **s != 0.0).count(), 3);
+
+        // and the voices of the chorus are on the center delay of 15 ms
+        let (_, samples) = render_process(
+            Chorus::new(1.0, 0.0),
+            vec![Box::new(Impulse(0))],
+            48000.0,
+            20,
+        );
+        assert!((samples[0] - 0.5).abs() < 1e-6);
+        assert!((samples[720] - 0.5).abs() < 1e-4);
+        assert_eq!(samples.iter().filter(|s| **s != 0.0).count(), 2);
+
+        // The sweep moves the delays, so the phase of the delayed sine
+        let fixed = render_process(
+            Chorus::new(1.0, 0.0),
+            vec![Box::new(SineWave::new(0.5, 440.0))],
+            48000.0,
+            200,
+        )
+        .1;
+        let swept = render_process(
+            Chorus::new(1.0, 1.0),
+            vec![Box::new(SineWave::new(0.5, 440.0))],
+            48000.0,
+            200,
+        )
+        .1;
+        assert!(fixed.iter().zip(&swept).any(|(f, s)| (f - s).abs() > 0.1));
+        let fixed = render_process(
+            Flanger::new(1.0, 0.0),
+            vec![Box::new(SineWave::new(0.5, 440.0))],
+            48000.0,
+            200,
+        )
+        .1;
+        let swept = render_process(
+            Flanger::new(1.0, 1.0),
+            vec![Box::new(SineWave::new(0.5, 440.0))],
+            48000.0,
+            200,
+        )
+        .1;
+        assert!(fixed.iter().zip(&swept).any(|(f, s)| (f - s).abs() > 0.1));
+    }
+
+    #[test]
+    fn modulation_effects_params() {
+        fn check<F: Process<f32> + Clone + Send + Sync + 'static>(effect: F, dry: fn(&mut F)) {
+            // A lot of feedback does not make the effect unstable
+            let processed = render_process(
+                effect.clone(),
+                vec![Box::new(SineWave::new(0.5, 440.0))],
+                48000.0,
+                1500,
+            )
+            .1;
+            assert!(processed.iter().all(|s| s.is_finite() && s.abs() < 10.0));
+
+            // Without mix the input goes through once the event is played
+            let sine = vec![Box::new(SineWave::new(0.5, 440.0)) as Box<dyn Process<f32>>];
+            let (mut audio, _) = render_process(effect, sine, 48000.0, 0);
+            let event = Event::update_params(dry, Duration::from_millis(500), &audio);
+            assert!(audio.register_event("node", event));
+            let processed = render(&mut audio, 1500, false);
+
+            let sine = render_process(
+                Mixer,
+                vec![Box::new(SineWave::new(0.5, 440.0))],
+                48000.0,
+                1500,
+            )
+            .1;
+            let at = audio.sample_idx_at(Duration::from_millis(500)).0;
+            assert!(processed[..at] != sine[..at]);
+            assert_eq!(processed[at..], sine[at..]);
+        }
+
+        let mut chorus = Chorus::new(2.0, 1.0);
+        chorus.params.feedback = 1.0;
+        check(chorus, |f: &mut Chorus| f.params.mix = 0.0);
+        let mut flanger = Flanger::new(0.5, 1.0);
+        flanger.params.feedback = -1.0;
+        check(flanger, |f: &mut Flanger| f.params.mix = 0.0);
+        let mut phaser = Phaser::new(0.5, 1.0);
+        phaser.params.feedback = 1.0;
+        check(phaser, |f: &mut Phaser| f.params.mix = 0.0);
+    }
 }
@@ -0,0 +1,156 @@
+// Attack, decay, sustain and release envelope driven by the note events
+//
+// A note on starts the attack from the current level and a note off starts the release,
+// so that retriggered notes and releases do not click. The node stays on: the release
+// rings out instead of the output being muted. Without input the envelope is the output,
+// to be used as a modulation, otherwise the first input is multiplied by the envelope.
+//
+// A change of the parameters is taken into account at the start of the next stage.
+#[derive(Clone)]
+pub struct Adsr {
+    pub params: AdsrParams,
+    stage: Stage,
+    level: f64,
+    // The level follows level = base + coef * level during the stage, computed when
+    // the stage starts, as the gate does not know the sample rate
+    slope: Option<(f64, f64)>,
+}
+
+#[derive(Clone)]
+pub struct AdsrParams {
+    // Durations of the stages in seconds
+    pub attack: f32,
+    pub decay: f32,
+    pub release: f32,
+    // Level of the sustain, between 0 and 1
+    pub sustain: f32,
+    pub curve: AdsrCurve,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdsrCurve {
+    Linear,
+    // Analog-like curves: the level moves exponentially toward a target beyond
+    // the end of the stage, so that the stage ends in time
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+// How far beyond the end of a stage the exponential curves aim
+const ATTACK_OVERSHOOT: f64 = 0.3;
+const DECAY_OVERSHOOT: f64 = 0.0001;
+
+impl Adsr {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        let params = AdsrParams {
+            attack,
+            decay,
+            release,
+            sustain,
+            curve: AdsrCurve::Linear,
+        };
+
+        Self {
+            params,
+            stage: Stage::Idle,
+            level: 0.0,
+            slope: None,
+        }
+    }
+
+    // Current level of the envelope
+    pub fn level(&self) -> f32 {
+        self.level as f32
+    }
+
+    fn enter(&mut self, stage: Stage) {
+        self.stage = stage;
+        self.slope = None;
+    }
+
+    // The slope going from the current level to `target` in `duration` seconds
+    fn slope(&self, target: f64, duration: f32, overshoot: f64, sample_rate: f32) -> (f64, f64) {
+        let num_samples = (duration as f64 * sample_rate as f64).max(1.0);
+
+        match self.params.curve {
+            AdsrCurve::Linear => ((target - self.level) / num_samples, 1.0),
+            AdsrCurve::Exponential => {
+                let overshoot = if target > self.level {
+                    overshoot
+                } else {
+                    -overshoot
+                };
+                // The distance to the aimed level shrinks by coef at each sample
+                let distance = (target + overshoot - self.level).abs();
+                let coef = (overshoot.abs() / distance).powf(1.0 / num_samples);
+                ((target + overshoot) * (1.0 - coef), coef)
+            }
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Adsr {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let sustain = self.params.sustain.clamp(0.0, 1.0) as f64;
+
+        if self.slope.is_none() {
+            let sr = ctx.sample_rate;
+            self.slope = Some(match self.stage {
+                Stage::Attack => self.slope(1.0, self.params.attack, ATTACK_OVERSHOOT, sr),
+                Stage::Decay => self.slope(sustain, self.params.decay, DECAY_OVERSHOOT, sr),
+                Stage::Release => self.slope(0.0, self.params.release, DECAY_OVERSHOOT, sr),
+                Stage::Idle | Stage::Sustain => (0.0, 1.0),
+            });
+        }
+        let (base, coef) = self.slope.unwrap();
+
+        match self.stage {
+            Stage::Idle => self.level = 0.0,
+            Stage::Sustain => self.level = sustain,
+            Stage::Attack => {
+                self.level = base + coef * self.level;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.enter(Stage::Decay);
+                }
+            }
+            Stage::Decay => {
+                self.level = base + coef * self.level;
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.enter(Stage::Sustain);
+                }
+            }
+            Stage::Release => {
+                self.level = base + coef * self.level;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.enter(Stage::Idle);
+                }
+            }
+        }
+
+        match inputs.first() {
+            Some(input) => input * self.level as f32,
+            None => self.level as f32,
+        }
+    }
+
+    fn gate(&mut self, on: bool) -> bool {
+        if on {
+            self.enter(Stage::Attack);
+        } else if self.stage != Stage::Idle {
+            self.enter(Stage::Release);
+        }
+        true
+    }
+}
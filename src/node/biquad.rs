@@ -0,0 +1,151 @@
+// Second order filter with the responses of the RBJ Audio EQ Cookbook
+//
+// The first input is filtered. The coefficients are computed again
+// every time the parameters or the sample rate change.
+#[derive(Clone)]
+pub struct Biquad {
+    pub params: BiquadParams,
+    coefs: Coefs,
+    // The parameters and the sample rate the coefficients have been computed for
+    computed: Option<(BiquadParams, f32)>,
+    // State of the transposed direct form II
+    z1: f64,
+    z2: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BiquadKind {
+    LowPass,
+    HighPass,
+    // Band pass with a gain of 0 dB at the cutoff frequency
+    BandPass,
+    Notch,
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadParams {
+    pub kind: BiquadKind,
+    // Cutoff or center frequency in Hz
+    pub cutoff: f32,
+    pub q: f32,
+    // Gain of the peaking and shelf filters
+    pub gain_db: f32,
+}
+
+// Coefficients normalized by a0
+#[derive(Clone, Copy, Default)]
+struct Coefs {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    pub fn new(kind: BiquadKind, cutoff: f32, q: f32, gain_db: f32) -> Self {
+        let params = BiquadParams {
+            kind,
+            cutoff,
+            q,
+            gain_db,
+        };
+
+        Self {
+            params,
+            coefs: Coefs::default(),
+            computed: None,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+impl Coefs {
+    fn new(params: &BiquadParams, sample_rate: f32) -> Self {
+        // Keep the filter stable whatever the parameters
+        let cutoff = params.cutoff.clamp(1.0, 0.499 * sample_rate) as f64;
+        let q = params.q.max(0.01) as f64;
+
+        let w0 = std::f64::consts::TAU * cutoff / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f64.powf(params.gain_db as f64 / 40.0);
+        let sqrt_a = a.sqrt();
+
+        let (b0, b1, b2, a0, a1, a2) = match params.kind {
+            BiquadKind::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            BiquadKind::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            BiquadKind::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            BiquadKind::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + 2.0 * sqrt_a * alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - 2.0 * sqrt_a * alpha),
+                (a + 1.0) + (a - 1.0) * cos + 2.0 * sqrt_a * alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - 2.0 * sqrt_a * alpha,
+            ),
+            BiquadKind::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + 2.0 * sqrt_a * alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - 2.0 * sqrt_a * alpha),
+                (a + 1.0) - (a - 1.0) * cos + 2.0 * sqrt_a * alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - 2.0 * sqrt_a * alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Biquad {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        if self.computed != Some((self.params, ctx.sample_rate)) {
+            self.coefs = Coefs::new(&self.params, ctx.sample_rate);
+            self.computed = Some((self.params, ctx.sample_rate));
+        }
+
+        let x = inputs.first().copied().unwrap_or(0.0) as f64;
+        let c = &self.coefs;
+
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+
+        y as f32
+    }
+}
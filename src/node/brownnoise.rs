@@ -0,0 +1,45 @@
+use super::rng::Rng;
+
+// Noise whose power decreases by 6 dB per octave, the integral of a white noise
+#[derive(Clone)]
+pub struct BrownNoise {
+    pub params: BrownNoiseParams,
+    rng: Rng,
+    last: f32,
+}
+
+#[derive(Clone)]
+pub struct BrownNoiseParams {
+    pub ampl: f32,
+}
+
+// Frequency in Hz below which the power stops increasing
+const LEAK_FREQ: f32 = 15.0;
+
+impl BrownNoise {
+    // The same seed always gives the same noise
+    pub fn new(ampl: f32, seed: u64) -> Self {
+        let params = BrownNoiseParams { ampl };
+        let rng = Rng::new(seed);
+        Self {
+            params,
+            rng,
+            last: 0.0,
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for BrownNoise {
+    fn process_next_value(&mut self, _: &[f32], ctx: &Context) -> f32 {
+        let white = self.rng.next_f32();
+
+        // The integration leaks below LEAK_FREQ so that the noise does not drift away from 0.
+        // The white noise is scaled so that the power does not depend on the sample rate
+        let leak = (-std::f32::consts::TAU * LEAK_FREQ / ctx.sample_rate).exp();
+        self.last = leak * self.last + (1.0 - leak * leak).sqrt() * white;
+
+        // Bring the noise back to about -1..1
+        self.last * 0.45 * self.params.ampl
+    }
+}
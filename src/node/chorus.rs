@@ -0,0 +1,80 @@
+use super::delayline::DelayLine;
+use super::polyblep::Phase;
+
+// Chorus: voices reading the input through delays slowly modulated by an LFO, each
+// voice with its own phase of the LFO
+//
+// The first input is processed. The buffer is allocated when the node enters a graph.
+#[derive(Clone)]
+pub struct Chorus {
+    pub params: ChorusParams,
+    line: DelayLine,
+    lfo: Phase,
+}
+
+#[derive(Clone)]
+pub struct ChorusParams {
+    // Frequency of the LFO in Hz
+    pub rate: f32,
+    // Between 0 and 1, how much of SWEEP the delays move
+    pub depth: f32,
+    // Part of the voices fed back into the delay line, between -1 and 1
+    pub feedback: f32,
+    // Part of the voices in the output, the rest being the input
+    pub mix: f32,
+}
+
+const NUM_VOICES: usize = 3;
+// Delay at the center of the sweep and largest distance to it, in seconds
+const CENTER: f32 = 0.015;
+const SWEEP: f32 = 0.005;
+
+impl Chorus {
+    pub fn new(rate: f32, depth: f32) -> Self {
+        let params = ChorusParams {
+            rate,
+            depth,
+            feedback: 0.0,
+            mix: 0.5,
+        };
+
+        Self {
+            params,
+            line: DelayLine::default(),
+            lfo: Phase::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Chorus {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        if self.line.is_empty() {
+            return input;
+        }
+
+        let (t, _) = self.lfo.next(self.params.rate, ctx.sample_rate);
+        let depth = self.params.depth.clamp(0.0, 1.0);
+
+        let wet = (0..NUM_VOICES)
+            .map(|voice| {
+                let t = t + voice as f64 / NUM_VOICES as f64;
+                let lfo = (t * std::f64::consts::TAU).sin() as f32;
+                let delay = (CENTER + depth * SWEEP * lfo) * ctx.sample_rate;
+                self.line.read(delay)
+            })
+            .sum::<f32>()
+            / NUM_VOICES as f32;
+
+        let feedback = self.params.feedback.clamp(-0.99, 0.99);
+        self.line.push(input + feedback * wet);
+
+        (1.0 - self.params.mix) * input + self.params.mix * wet
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block: usize) {
+        self.line
+            .allocate(((CENTER + SWEEP) * sample_rate) as usize + 2);
+    }
+}
@@ -0,0 +1,58 @@
+use super::dynamics::{compression_gain, from_db, signal_and_key, to_db, Smoother};
+
+// Downward compressor reducing the levels above the threshold by the ratio
+//
+// The first input is compressed, its level being read from the key given by `signal_and_key`.
+#[derive(Clone)]
+pub struct Compressor {
+    pub params: CompressorParams,
+    smoother: Smoother,
+}
+
+#[derive(Clone)]
+pub struct CompressorParams {
+    // Level in dB above which the signal is compressed
+    pub threshold: f32,
+    // How many dB above the threshold at the input give 1 dB at the output
+    pub ratio: f32,
+    // Width in dB of the soft transition around the threshold
+    pub knee: f32,
+    // Times in seconds for the gain to go down and back up
+    pub attack: f32,
+    pub release: f32,
+    // Gain in dB applied after the compression
+    pub makeup: f32,
+}
+
+impl Compressor {
+    pub fn new(threshold: f32, ratio: f32) -> Self {
+        let params = CompressorParams {
+            threshold,
+            ratio,
+            knee: 0.0,
+            attack: 0.01,
+            release: 0.1,
+            makeup: 0.0,
+        };
+
+        Self {
+            params,
+            smoother: Smoother::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Compressor {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (input, key) = signal_and_key(inputs);
+
+        let p = &self.params;
+        let target = compression_gain(to_db(key), p.threshold, p.ratio, p.knee);
+        let gain = self
+            .smoother
+            .next(target, p.attack, p.release, ctx.sample_rate);
+
+        input * from_db(gain + p.makeup)
+    }
+}
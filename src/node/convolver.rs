@@ -0,0 +1,167 @@
+use crate::fft::{Complex, Fft};
+
+use std::sync::Arc;
+
+// Convolution of the first input by an impulse response
+//
+// The impulse response is cut in partitions of the size of the blocks of the graph, and
+// the input is convolved one block at a time in the frequency domain (uniformly
+// partitioned overlap-save). The output is thus late by one block of the graph.
+// The spectra of the partitions and the buffers are computed when the node enters a graph.
+#[derive(Clone)]
+pub struct Convolver {
+    pub params: ConvolverParams,
+    // The impulse response is shared by the clones of the node
+    ir: Arc<Vec<f32>>,
+    state: Option<State>,
+}
+
+#[derive(Clone)]
+pub struct ConvolverParams {
+    // Part of the convolved signal in the output, the rest being the input
+    pub mix: f32,
+}
+
+#[derive(Clone)]
+struct State {
+    block_size: usize,
+    fft: Fft,
+    // Spectra of the partitions of the impulse response, zero-padded
+    partitions: Vec<Vec<Complex>>,
+    // Spectra of the last input windows, one per partition, most recent at `idx_spectrum`
+    spectra: Vec<Vec<Complex>>,
+    idx_spectrum: usize,
+    // The last samples of input, the current block being at the end
+    window: Vec<f32>,
+    // The input delayed by a block, for the dry signal to line up with the convolved one
+    dry: Vec<f32>,
+    // The convolved block being played
+    output: Vec<f32>,
+    // Position in the current block
+    pos: usize,
+    // Scratch buffers of the transforms
+    buf: Vec<Complex>,
+    sum: Vec<Complex>,
+}
+
+impl Convolver {
+    pub fn new(ir: Vec<f32>) -> Self {
+        Self {
+            params: ConvolverParams { mix: 1.0 },
+            ir: Arc::new(ir),
+            state: None,
+        }
+    }
+
+    // Load the impulse response from the first channel of a WAV file
+    pub fn from_wav<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::new(super::wav::read_first_channel(path)?))
+    }
+
+    // Latency of the convolution in samples, known once the node is in a graph
+    pub fn latency(&self) -> Option<usize> {
+        self.state.as_ref().map(|state| state.block_size)
+    }
+}
+
+impl State {
+    fn new(ir: &[f32], block_size: usize) -> Self {
+        // The transforms are long enough for the convolution of a partition and a block
+        // not to wrap around, and a power of two
+        let block_size = block_size.max(1);
+        let fft_len = (2 * block_size).next_power_of_two();
+        let fft = Fft::new(fft_len);
+
+        let partitions = ir
+            .chunks(block_size)
+            .map(|partition| {
+                let mut spectrum = vec![Complex::default(); fft_len];
+                for (bin, s) in spectrum.iter_mut().zip(partition) {
+                    *bin = Complex::new(*s, 0.0);
+                }
+                fft.forward(&mut spectrum);
+                spectrum
+            })
+            .collect::<Vec<_>>();
+        let num_partitions = partitions.len().max(1);
+
+        Self {
+            block_size,
+            fft,
+            partitions,
+            spectra: vec![vec![Complex::default(); fft_len]; num_partitions],
+            idx_spectrum: 0,
+            window: vec![0.0; fft_len],
+            dry: vec![0.0; block_size],
+            output: vec![0.0; block_size],
+            pos: 0,
+            buf: vec![Complex::default(); fft_len],
+            sum: vec![Complex::default(); fft_len],
+        }
+    }
+
+    // Convolve the window of input and replace the output by the result
+    fn convolve_block(&mut self) {
+        let num_partitions = self.spectra.len();
+        self.idx_spectrum = (self.idx_spectrum + 1) % num_partitions;
+
+        let spectrum = &mut self.spectra[self.idx_spectrum];
+        for (bin, s) in spectrum.iter_mut().zip(self.window.iter()) {
+            *bin = Complex::new(*s, 0.0);
+        }
+        self.fft.forward(spectrum);
+
+        // The partition p applies to the window of p blocks ago
+        self.sum.fill(Complex::default());
+        for (p, partition) in self.partitions.iter().enumerate() {
+            let spectrum = &self.spectra[(self.idx_spectrum + num_partitions - p) % num_partitions];
+            for ((sum, x), h) in self.sum.iter_mut().zip(spectrum).zip(partition) {
+                *sum = *sum + *x * *h;
+            }
+        }
+
+        self.buf.copy_from_slice(&self.sum);
+        self.fft.inverse(&mut self.buf);
+
+        // Only the end of the circular convolution is the linear one
+        let fft_len = self.buf.len();
+        for (out, bin) in self
+            .output
+            .iter_mut()
+            .zip(&self.buf[fft_len - self.block_size..])
+        {
+            *out = bin.re;
+        }
+
+        self.window.copy_within(self.block_size.., 0);
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Convolver {
+    fn process_next_value(&mut self, inputs: &[f32], _: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        let state = match self.state.as_mut() {
+            Some(state) => state,
+            None => return input,
+        };
+
+        let pos = state.pos;
+        let wet = state.output[pos];
+        let dry = std::mem::replace(&mut state.dry[pos], input);
+        let len = state.window.len();
+        state.window[len - state.block_size + pos] = input;
+
+        state.pos += 1;
+        if state.pos == state.block_size {
+            state.convolve_block();
+            state.pos = 0;
+        }
+
+        (1.0 - self.params.mix) * dry + self.params.mix * wet
+    }
+
+    fn prepare(&mut self, _sample_rate: f32, max_block: usize) {
+        self.state = Some(State::new(&self.ir, max_block));
+    }
+}
@@ -0,0 +1,127 @@
+use super::delayline::DelayLine;
+
+// Delay line with feedback
+//
+// The first input is delayed. The delay time glides toward the one set in the params
+// instead of jumping, so that changes of the delay time by events do not click. The
+// buffer is allocated when the node enters a graph, for delays up to `max_time`.
+#[derive(Clone)]
+pub struct Delay {
+    pub params: DelayParams,
+    max_time: f32,
+    line: DelayLine,
+    // Delay in samples, following the delay time of the params
+    delay: Option<f64>,
+    // Last output of the allpass interpolation
+    allpass: f32,
+}
+
+#[derive(Clone)]
+pub struct DelayParams {
+    // Delay time in seconds
+    pub time: f32,
+    // Delay time in beats, replacing `time` if set
+    pub beats: Option<f32>,
+    // Part of the output fed back into the delay line, between -1 and 1
+    pub feedback: f32,
+    // Part of the delayed signal in the output, the rest being the input
+    pub mix: f32,
+    pub interpolation: DelayInterpolation,
+}
+
+// How the delay line is read between two samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DelayInterpolation {
+    Linear,
+    // Cubic Hermite interpolation of four samples, for fewer high frequency losses
+    Hermite,
+    // First order allpass, flat in frequency. Suited to constant delays such as combs
+    Allpass,
+}
+
+// Shortest delay in samples, for the Hermite interpolation to read samples already written
+const MIN_DELAY: f32 = 2.0;
+// Time constant of the glide of the delay time, in seconds
+const GLIDE_TIME: f32 = 0.05;
+
+impl Delay {
+    pub fn new(time: f32, max_time: f32) -> Self {
+        let params = DelayParams {
+            time,
+            beats: None,
+            feedback: 0.0,
+            mix: 1.0,
+            interpolation: DelayInterpolation::Linear,
+        };
+
+        Self {
+            params,
+            max_time,
+            line: DelayLine::default(),
+            delay: None,
+            allpass: 0.0,
+        }
+    }
+
+    fn read(&mut self, delay: f32) -> f32 {
+        let idx = delay as usize;
+        let frac = delay - idx as f32;
+
+        match self.params.interpolation {
+            DelayInterpolation::Linear => self.line.read(delay),
+            DelayInterpolation::Hermite => {
+                let line = &self.line;
+                let (ym1, y0, y1, y2) = (
+                    line.tap(idx - 1),
+                    line.tap(idx),
+                    line.tap(idx + 1),
+                    line.tap(idx + 2),
+                );
+                let c1 = 0.5 * (y1 - ym1);
+                let c2 = ym1 - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+                let c3 = 0.5 * (y2 - ym1) + 1.5 * (y0 - y1);
+                ((c3 * frac + c2) * frac + c1) * frac + y0
+            }
+            DelayInterpolation::Allpass => {
+                let eta = (1.0 - frac) / (1.0 + frac);
+                self.allpass = eta * (self.line.tap(idx) - self.allpass) + self.line.tap(idx + 1);
+                self.allpass
+            }
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Delay {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        if self.line.is_empty() {
+            return input;
+        }
+
+        let target = match self.params.beats {
+            Some(beats) => beats * ctx.samples_per_beat(),
+            None => self.params.time * ctx.sample_rate,
+        };
+        let target = target.clamp(MIN_DELAY, (self.line.len() - 3) as f32) as f64;
+        let coef = 1.0 - (-1.0 / (GLIDE_TIME as f64 * ctx.sample_rate as f64)).exp();
+        let delay = match self.delay {
+            // The glide stops when close enough, as it would get stuck by rounding
+            Some(delay) if (target - delay).abs() > 1e-6 => delay + coef * (target - delay),
+            _ => target,
+        };
+        self.delay = Some(delay);
+
+        let delayed = self.read(delay as f32);
+        let feedback = self.params.feedback.clamp(-0.99, 0.99);
+        self.line.push(input + feedback * delayed);
+
+        (1.0 - self.params.mix) * input + self.params.mix * delayed
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block: usize) {
+        // Room for the samples around the longest delay read by the interpolation
+        let len = (self.max_time * sample_rate).ceil() as usize + 4;
+        self.line.allocate(len.max(MIN_DELAY as usize + 4));
+    }
+}
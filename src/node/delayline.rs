@@ -0,0 +1,43 @@
+// Circular buffer holding the last samples of a signal
+#[derive(Clone, Default)]
+pub(crate) struct DelayLine {
+    buffer: Vec<f32>,
+    // Index of the next sample written
+    write: usize,
+}
+
+impl DelayLine {
+    // Make room for `len` samples, all silent
+    pub(crate) fn allocate(&mut self, len: usize) {
+        self.buffer = vec![0.0; len];
+        self.write = 0;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, value: f32) {
+        self.buffer[self.write] = value;
+        self.write = (self.write + 1) % self.buffer.len();
+    }
+
+    // Sample pushed `delay` samples before the next one, between 1 and the length
+    pub(crate) fn tap(&self, delay: usize) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[(self.write + len - delay) % len]
+    }
+
+    // Sample pushed `delay` samples before the next one, linearly interpolated
+    pub(crate) fn read(&self, delay: f32) -> f32 {
+        let idx = delay as usize;
+        let frac = delay - idx as f32;
+        let (a, b) = (self.tap(idx), self.tap(idx + 1));
+
+        a + frac * (b - a)
+    }
+}
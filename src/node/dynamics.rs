@@ -0,0 +1,67 @@
+// Building blocks of the dynamics processors: the static curves giving the gain for a
+// level, and the smoothing of the gain by the attack and release times.
+// Levels and gains are in dB.
+
+// Level below which a signal is considered silent
+pub(crate) const SILENCE: f32 = -120.0;
+
+// The signal processed by a dynamics processor, its first input, and the key the level is
+// read from. The key is the second input if any, as a sidechain, otherwise the first one
+pub(crate) fn signal_and_key(inputs: &[f32]) -> (f32, f32) {
+    let input = inputs.first().copied().unwrap_or(0.0);
+    (input, inputs.get(1).copied().unwrap_or(input))
+}
+
+pub(crate) fn to_db(value: f32) -> f32 {
+    (20.0 * value.abs().log10()).max(SILENCE)
+}
+
+pub(crate) fn from_db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// Gain reducing the levels above `threshold` by `ratio`, with a quadratic knee of `knee` dB
+// around the threshold. An infinite ratio keeps the levels at the threshold
+pub(crate) fn compression_gain(level: f32, threshold: f32, ratio: f32, knee: f32) -> f32 {
+    let slope = 1.0 / ratio.max(1.0) - 1.0;
+    let over = level - threshold;
+
+    if 2.0 * over < -knee {
+        0.0
+    } else if 2.0 * over <= knee && knee > 0.0 {
+        slope * (over + knee / 2.0).powi(2) / (2.0 * knee)
+    } else {
+        slope * over
+    }
+}
+
+// Gain increasing by `ratio` the distance of the levels below `threshold` to it, with a
+// quadratic knee of `knee` dB around the threshold
+pub(crate) fn expansion_gain(level: f32, threshold: f32, ratio: f32, knee: f32) -> f32 {
+    let slope = ratio.max(1.0) - 1.0;
+    let over = level - threshold;
+
+    if 2.0 * over > knee {
+        0.0
+    } else if 2.0 * over >= -knee && knee > 0.0 {
+        -slope * (over - knee / 2.0).powi(2) / (2.0 * knee)
+    } else {
+        slope * over
+    }
+}
+
+// Gain following a target with the time `down` when the gain goes down and the time `up`
+// when it goes back up
+#[derive(Clone, Default)]
+pub(crate) struct Smoother {
+    gain: f32,
+}
+
+impl Smoother {
+    pub(crate) fn next(&mut self, target: f32, down: f32, up: f32, sample_rate: f32) -> f32 {
+        let time = if target < self.gain { down } else { up };
+        let coef = (-1.0 / (time * sample_rate).max(1e-3)).exp();
+        self.gain = target + coef * (self.gain - target);
+        self.gain
+    }
+}
@@ -0,0 +1,62 @@
+use super::dynamics::{expansion_gain, from_db, signal_and_key, to_db, Smoother};
+
+// Downward expander pushing the levels below the threshold further down by the ratio
+//
+// The first input is expanded, its level being read from the key given by `signal_and_key`.
+#[derive(Clone)]
+pub struct Expander {
+    pub params: ExpanderParams,
+    smoother: Smoother,
+}
+
+#[derive(Clone)]
+pub struct ExpanderParams {
+    // Level in dB below which the signal is expanded
+    pub threshold: f32,
+    // How many dB below the threshold at the output for 1 dB at the input
+    pub ratio: f32,
+    // Width in dB of the soft transition around the threshold
+    pub knee: f32,
+    // Largest reduction of the gain in dB
+    pub range: f32,
+    // Time in seconds for the gain to go back up when the level rises
+    pub attack: f32,
+    // Time in seconds for the gain to go down when the level falls
+    pub release: f32,
+    // Gain in dB applied after the expansion
+    pub makeup: f32,
+}
+
+impl Expander {
+    pub fn new(threshold: f32, ratio: f32) -> Self {
+        let params = ExpanderParams {
+            threshold,
+            ratio,
+            knee: 0.0,
+            range: 60.0,
+            attack: 0.001,
+            release: 0.1,
+            makeup: 0.0,
+        };
+
+        Self {
+            params,
+            smoother: Smoother::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Expander {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (input, key) = signal_and_key(inputs);
+
+        let p = &self.params;
+        let target = expansion_gain(to_db(key), p.threshold, p.ratio, p.knee).max(-p.range);
+        let gain = self
+            .smoother
+            .next(target, p.release, p.attack, ctx.sample_rate);
+
+        input * from_db(gain + p.makeup)
+    }
+}
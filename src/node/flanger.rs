@@ -0,0 +1,73 @@
+use super::delayline::DelayLine;
+use super::polyblep::Phase;
+
+// Flanger: the input mixed with itself through a short delay swept by an LFO, the
+// feedback deepening the comb filter it makes
+//
+// The first input is processed. The buffer is allocated when the node enters a graph.
+#[derive(Clone)]
+pub struct Flanger {
+    pub params: FlangerParams,
+    line: DelayLine,
+    lfo: Phase,
+}
+
+#[derive(Clone)]
+pub struct FlangerParams {
+    // Frequency of the LFO in Hz
+    pub rate: f32,
+    // Between 0 and 1, how much of SWEEP the delay moves above MIN_DELAY
+    pub depth: f32,
+    // Part of the delayed signal fed back into the delay line, between -1 and 1
+    pub feedback: f32,
+    // Part of the delayed signal in the output, the rest being the input
+    pub mix: f32,
+}
+
+// Shortest delay and largest sweep above it, in seconds
+const MIN_DELAY: f32 = 0.0001;
+const SWEEP: f32 = 0.005;
+
+impl Flanger {
+    pub fn new(rate: f32, depth: f32) -> Self {
+        let params = FlangerParams {
+            rate,
+            depth,
+            feedback: 0.5,
+            mix: 0.5,
+        };
+
+        Self {
+            params,
+            line: DelayLine::default(),
+            lfo: Phase::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Flanger {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        if self.line.is_empty() {
+            return input;
+        }
+
+        // The delay goes from MIN_DELAY up and back down
+        let (t, _) = self.lfo.next(self.params.rate, ctx.sample_rate);
+        let lfo = 0.5 - 0.5 * (t * std::f64::consts::TAU).cos() as f32;
+        let depth = self.params.depth.clamp(0.0, 1.0);
+        let delay = ((MIN_DELAY + depth * SWEEP * lfo) * ctx.sample_rate).max(1.0);
+        let delayed = self.line.read(delay);
+
+        let feedback = self.params.feedback.clamp(-0.99, 0.99);
+        self.line.push(input + feedback * delayed);
+
+        (1.0 - self.params.mix) * input + self.params.mix * delayed
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block: usize) {
+        self.line
+            .allocate(((MIN_DELAY + SWEEP) * sample_rate) as usize + 2);
+    }
+}
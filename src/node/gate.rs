@@ -0,0 +1,68 @@
+use super::dynamics::{from_db, signal_and_key, to_db, Smoother};
+
+// Noise gate closing when the level falls below the threshold
+//
+// Below the threshold the gain drops by `range` dB, the knee moving it linearly in dB
+// around the threshold. The first input is gated, its level being read from the key given
+// by `signal_and_key`.
+#[derive(Clone)]
+pub struct Gate {
+    pub params: GateParams,
+    smoother: Smoother,
+}
+
+#[derive(Clone)]
+pub struct GateParams {
+    // Level in dB below which the gate closes
+    pub threshold: f32,
+    // Width in dB of the soft transition around the threshold
+    pub knee: f32,
+    // Reduction of the gain in dB when the gate is closed
+    pub range: f32,
+    // Time in seconds for the gate to open when the level rises above the threshold
+    pub attack: f32,
+    // Time in seconds for the gate to close when the level falls below the threshold
+    pub release: f32,
+    // Gain in dB applied after the gate
+    pub makeup: f32,
+}
+
+impl Gate {
+    pub fn new(threshold: f32) -> Self {
+        let params = GateParams {
+            threshold,
+            knee: 0.0,
+            range: 80.0,
+            attack: 0.001,
+            release: 0.05,
+            makeup: 0.0,
+        };
+
+        Self {
+            params,
+            smoother: Smoother::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Gate {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (input, key) = signal_and_key(inputs);
+
+        let p = &self.params;
+        let over = to_db(key) - p.threshold;
+        let target = if 2.0 * over >= p.knee {
+            0.0
+        } else if 2.0 * over <= -p.knee {
+            -p.range
+        } else {
+            -p.range * (0.5 - over / p.knee)
+        };
+        let gain = self
+            .smoother
+            .next(target, p.release, p.attack, ctx.sample_rate);
+
+        input * from_db(gain + p.makeup)
+    }
+}
@@ -0,0 +1,69 @@
+// Moog-style 24 dB/octave low-pass filter
+//
+// Four zero-delay feedback one-pole stages with the feedback loop solved for each
+// sample, after Vadim Zavalishin's "The Art of VA Filter Design". The input of the
+// ladder is saturated so that the filter self-oscillates without blowing up at
+// maximum resonance. The first input is filtered and the second one, if any, moves
+// the cutoff by `depth` octaves per unit.
+#[derive(Clone)]
+pub struct LadderFilter {
+    pub params: LadderFilterParams,
+    // The states of the four stages
+    stages: [f64; 4],
+}
+
+#[derive(Clone)]
+pub struct LadderFilterParams {
+    // Cutoff frequency in Hz
+    pub cutoff: f32,
+    // Resonance between 0 and 1, the filter self-oscillates at 1
+    pub resonance: f32,
+    // Modulation of the cutoff by the second input, in octaves
+    pub depth: f32,
+}
+
+impl LadderFilter {
+    pub fn new(cutoff: f32, resonance: f32) -> Self {
+        let params = LadderFilterParams {
+            cutoff,
+            resonance,
+            depth: 1.0,
+        };
+
+        Self {
+            params,
+            stages: [0.0; 4],
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for LadderFilter {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0) as f64;
+        let modulation = inputs.get(1).copied().unwrap_or(0.0);
+        let cutoff = self.params.cutoff * (self.params.depth * modulation).exp2();
+        let cutoff = cutoff.clamp(1.0, 0.49 * ctx.sample_rate) as f64;
+
+        let g = (std::f64::consts::PI * cutoff / ctx.sample_rate as f64).tan();
+        let gain = g / (1.0 + g);
+        // The loop gain goes slightly above 1 so that the oscillation builds up at maximum resonance
+        let k = 4.2 * self.params.resonance.clamp(0.0, 1.0) as f64;
+
+        // The output of the ladder is gain^4 * u + s, `s` depending on the states only
+        let s = self
+            .stages
+            .iter()
+            .fold(0.0, |s, state| s * gain + state / (1.0 + g));
+        let u = ((input - k * s) / (1.0 + k * gain.powi(4))).tanh();
+
+        let mut x = u;
+        for state in self.stages.iter_mut() {
+            let v = (x - *state) * gain;
+            x = v + *state;
+            *state = x + v;
+        }
+
+        x as f32
+    }
+}
@@ -0,0 +1,147 @@
+use super::dynamics::{compression_gain, from_db, signal_and_key, to_db};
+
+// Limiter keeping the peaks under the threshold
+//
+// The input is delayed by the lookahead so that the gain has gone down when a peak
+// comes out: the gain is the lowest one needed over the lookahead, smoothed over the
+// lookahead as well, both in constant time per sample. The first input is limited, its
+// level being read from the key given by `signal_and_key`. The buffers are allocated
+// when the node enters a graph.
+#[derive(Clone)]
+pub struct Limiter {
+    pub params: LimiterParams,
+    lookahead: f32,
+    // The lowest gain needed by the last samples, the gains after the release and
+    // the last samples of input, over the lookahead
+    needed: SlidingMin,
+    released: Vec<f32>,
+    delay: Vec<f32>,
+    idx: usize,
+    gain: f32,
+    // Sum of the released gains, updated with each new gain
+    sum: f64,
+}
+
+// Minimum of the last values, kept in a monotonic deque: the values are increasing
+// from the front to the back, a value being dropped once a lower one comes after it
+// or once it is out of the window
+#[derive(Clone, Default)]
+struct SlidingMin {
+    // Ring buffer of the values with their index, as many as the window holds
+    deque: Vec<(usize, f32)>,
+    front: usize,
+    len: usize,
+    // Index of the next value
+    next: usize,
+}
+
+impl SlidingMin {
+    fn allocate(&mut self, window: usize) {
+        *self = Self {
+            deque: vec![(0, 0.0); window],
+            ..Self::default()
+        };
+    }
+
+    // Add a value and give the minimum of the window ending with it
+    fn push(&mut self, value: f32) -> f32 {
+        let window = self.deque.len();
+        let back = |min: &Self| (min.front + min.len - 1) % window;
+
+        while self.len > 0 && self.deque[back(self)].1 >= value {
+            self.len -= 1;
+        }
+        if self.len > 0 && self.deque[self.front].0 + window <= self.next {
+            self.front = (self.front + 1) % window;
+            self.len -= 1;
+        }
+        self.len += 1;
+        let idx = back(self);
+        self.deque[idx] = (self.next, value);
+        self.next += 1;
+
+        self.deque[self.front].1
+    }
+}
+
+#[derive(Clone)]
+pub struct LimiterParams {
+    // Level in dB of the highest peaks
+    pub threshold: f32,
+    // Width in dB of the soft transition around the threshold
+    pub knee: f32,
+    // Time in seconds for the gain to go back up
+    pub release: f32,
+    // Gain in dB applied after the limiter
+    pub makeup: f32,
+}
+
+impl Limiter {
+    // A limiter looking `lookahead` seconds ahead, which is its latency
+    pub fn new(threshold: f32, lookahead: f32) -> Self {
+        let params = LimiterParams {
+            threshold,
+            knee: 0.0,
+            release: 0.05,
+            makeup: 0.0,
+        };
+
+        Self {
+            params,
+            lookahead,
+            needed: SlidingMin::default(),
+            released: vec![],
+            delay: vec![],
+            idx: 0,
+            gain: 1.0,
+            sum: 0.0,
+        }
+    }
+
+    // Latency of the limiter in samples, known once the node is in a graph
+    pub fn latency(&self) -> Option<usize> {
+        (!self.delay.is_empty()).then(|| self.delay.len() - 1)
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Limiter {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (input, key) = signal_and_key(inputs);
+        if self.delay.is_empty() {
+            return input;
+        }
+
+        let p = &self.params;
+        let needed = compression_gain(to_db(key), p.threshold, f32::INFINITY, p.knee);
+        let held = self.needed.push(from_db(needed));
+
+        // The gain goes down at once, the smoothing below acting as the attack
+        let coef = (-1.0 / (p.release * ctx.sample_rate).max(1e-3)).exp();
+        self.gain = if held < self.gain {
+            held
+        } else {
+            held + coef * (self.gain - held)
+        };
+        let released = std::mem::replace(&mut self.released[self.idx], self.gain);
+        self.sum += self.gain as f64 - released as f64;
+        let gain = (self.sum / self.released.len() as f64) as f32;
+
+        // The sample written a lookahead ago
+        self.delay[self.idx] = input;
+        self.idx = (self.idx + 1) % self.delay.len();
+        let delayed = self.delay[self.idx];
+
+        delayed * gain * from_db(p.makeup)
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block: usize) {
+        let len = (self.lookahead * sample_rate) as usize + 1;
+        self.needed.allocate(len);
+        self.released = vec![1.0; len];
+        self.delay = vec![0.0; len];
+        self.idx = 0;
+        self.gain = 1.0;
+        self.sum = len as f64;
+    }
+}
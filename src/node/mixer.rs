@@ -1,9 +1,9 @@
 /* Mixer */
 #[derive(Clone)]
 pub struct Mixer;
-use super::Process;
+use super::{Context, Process};
 impl Process<f32> for Mixer {
-    fn process_next_value(&mut self, inputs: &[f32]) -> f32 {
+    fn process_next_value(&mut self, inputs: &[f32], _: &Context) -> f32 {
         inputs.iter().sum::<f32>()
     }
 }
@@ -1,35 +1,34 @@
-use std::collections::HashMap;
 use std::marker::Send;
-use std::sync::{Arc, Mutex};
-const MAX_NODE_INPUTS: usize = 8;
-
-use rtrb::RingBuffer;
 
 pub struct Node<S, F, const N: usize>
 where
     S: rodio::Sample + Send + Sync + 'static,
     F: Process<S> + Clone + 'static,
 {
-    buf: [S; N],
     pub name: &'static str,
     pub f: F,     // Process
     pub on: bool, // process on
 
     events: Vec<Event<S, F, N>>,
-
-    parents: HashMap<&'static str, Arc<Mutex<dyn NodeTrait<S, N>>>>,
+    // Ramps of the parameters being played
+    ramps: Vec<Ramp<F>>,
+
+    // Inputs added before the node enters a graph, kept in insertion order.
+    // They are moved into the graph render plan when the node is added to a graph
+    parents: Vec<Box<dyn NodeTrait<S, N>>>,
+    // Values of the inputs for the sample being processed
+    input: Vec<S>,
+    // Whether the process and the inputs have been prepared for a graph
+    prepared: bool,
 }
-pub(crate) type Nodes<S, const N: usize> = HashMap<&'static str, Arc<Mutex<dyn NodeTrait<S, N>>>>;
 
+use crate::event::{GraphEvent, Ramp};
+// Number of events a node holds before its queue has to grow, which would allocate
+// on the thread rendering the graph
+const EVENTS_CAPACITY: usize = 16;
 use crate::Event;
 
-// Utilitary method to convert an allocated array on the heap
-// to a sized boxed slice
-unsafe fn vec_to_boxed_slice<T, const N: usize>(input: Vec<T>) -> Box<[T; N]> {
-    let input = input.into_boxed_slice();
-    Box::from_raw(Box::into_raw(input) as *mut [T; N])
-}
-
+use crate::context::Context;
 use crate::sampling::SampleIdx;
 impl<S, F, const N: usize> Node<S, F, N>
 where
@@ -38,12 +37,14 @@ where
 {
     pub fn new(name: &'static str, f: F) -> Self {
         Self {
-            buf: [S::zero_value(); N],
-            f: f,
+            f,
             on: true,
-            name: name,
-            parents: HashMap::new(),
-            events: vec![],
+            name,
+            parents: vec![],
+            events: Vec::with_capacity(EVENTS_CAPACITY),
+            ramps: vec![],
+            input: vec![],
+            prepared: false,
         }
     }
 
@@ -51,77 +52,107 @@ where
     where
         F2: Process<S> + Clone + 'static,
     {
-        self.parents.insert(input.name, Arc::new(Mutex::new(input)));
+        self.parents.push(Box::new(input));
         self
     }
 
     pub fn get_name(&self) -> &str {
-        &self.name
+        self.name
     }
 
     // Register the event in the node or its children
     // return true if a node has been found
     pub fn register_event(&mut self, event: Event<S, F, N>) {
-        // Add the event to the current node
-        self.events.push(event);
-        // sort by sample idx so that we can only execute the first one(s)
-        self.events.sort();
+        // Keep the events sorted by decreasing sample indices so that we can only execute
+        // the last one(s). The insertion does not allocate until EVENTS_CAPACITY is reached
+        let sample = event.get_sample_idx();
+        let idx = self
+            .events
+            .partition_point(|e| e.get_sample_idx() >= sample);
+        self.events.insert(idx, event);
+    }
+
+    // Play the events occuring at or before the absolute sample index `sample`
+    fn play_events(&mut self, sample: SampleIdx) {
+        // As events is sorted by decreasing sample indices, we can only check the last event to be played
+        while self
+            .events
+            .last()
+            .is_some_and(|event| event.get_sample_idx() <= sample)
+        {
+            let event = self.events.pop().unwrap();
+            event.play_on(self);
+        }
+    }
+
+    // Start a ramp, replacing the one being played on the same parameter
+    pub(crate) fn start_ramp(&mut self, ramp: Ramp<F>) {
+        let param: *const f32 = (ramp.param)(&mut self.f);
+        let f = &mut self.f;
+        self.ramps
+            .retain(|other| !std::ptr::eq((other.param)(f), param));
+
+        self.ramps.push(ramp);
+    }
+
+    // Set the parameters being ramped to their value at the sample `sample`
+    fn play_ramps(&mut self, sample: SampleIdx) {
+        let f = &mut self.f;
+        self.ramps.retain(|ramp| {
+            let (value, over) = ramp.value_at(sample);
+            *(ramp.param)(f) = value;
+            !over
+        });
+    }
+
+    // Compute the value of the node for the sample described by `ctx`
+    //
+    // This is the only place where events are dispatched so that every
+    // streaming method (block, rtrb and iterator) gives the same result
+    fn process_sample(&mut self, ctx: &Context, inputs: &[S]) -> S {
+        self.play_events(ctx.sample);
+        self.play_ramps(ctx.sample);
+
+        if self.on {
+            self.f.process_next_value(inputs, ctx)
+        } else {
+            S::zero_value()
+        }
     }
 }
 
-use std::collections::HashSet;
-// The Node trait responsible for retrieving
+use std::ops::Range;
+// The Node trait responsible for processing the samples of a node of the render plan
 use std::any::Any;
-pub trait NodeTrait<S, const N: usize>: Iterator<Item = S> + Send
+pub trait NodeTrait<S, const N: usize>: Send
 where
     S: rodio::Sample + Send + Sync + 'static,
 {
-    fn stream_into(&mut self, buf: &mut Box<[S; N]>, multithreading: bool);
-    fn stream_into_rtrb(&mut self, multithreading: bool);
-
-    fn collect_nodes(&self, nodes: &mut Nodes<S, N>);
-
-    fn delete_node(
-        &mut self,
-        name: &'static str,
-        nodes_to_remove: &mut HashSet<&'static str>,
-    ) -> bool;
-    fn delete_parents_hierarchy(&mut self, nodes_to_remove: &mut HashSet<&'static str>);
-
-    fn add_input_trait_object(
+    /// Process the samples of the block whose first sample is described by `ctx`
+    /// and whose indices are in `range`. The values of the inputs are read from `bufs` at the
+    /// indices given by `inputs` and the result is written in `out`.
+    fn process(
         &mut self,
-        name: &'static str,
-        input: Arc<Mutex<dyn NodeTrait<S, N>>>,
+        ctx: &Context,
+        range: Range<usize>,
+        bufs: &[[S; N]],
+        inputs: &[usize],
+        out: &mut [S; N],
     );
-    fn get_name(&self) -> &'static str;
-    fn as_mut_any(&mut self) -> &mut dyn Any;
 
-    fn get_buf(&self) -> &[S; N];
-}
-
-use std::cell::UnsafeCell;
+    /// Called before the node enters a graph, before it processes any sample.
+    /// The inputs added to the node before it entered the graph are prepared as well
+    fn prepare(&mut self, sample_rate: f32, max_block: usize);
+    /// Whether `prepare` has been called, so that the graph does not prepare the node again
+    fn is_prepared(&self) -> bool;
 
-#[derive(Copy, Clone)]
-pub struct UnsafeSlice<'a, T> {
-    slice: &'a [UnsafeCell<T>],
-}
-unsafe impl<'a, T: Send + Sync> Send for UnsafeSlice<'a, T> {}
-unsafe impl<'a, T: Send + Sync> Sync for UnsafeSlice<'a, T> {}
+    /// Give the inputs added to the node before it entered a graph
+    fn take_inputs(&mut self) -> Vec<Box<dyn NodeTrait<S, N>>>;
+    /// Move the events changing the graph topology registered on the node to `events`
+    fn take_graph_events(&mut self, events: &mut Vec<GraphEvent<S, N>>);
 
-impl<'a, T> UnsafeSlice<'a, T> {
-    pub fn new(slice: &'a mut [T]) -> Self {
-        let ptr = slice as *mut [T] as *const [UnsafeCell<T>];
-        Self {
-            slice: unsafe { &*ptr },
-        }
-    }
-    
-    /// SAFETY: It is UB if two threads write to the same index without
-    /// synchronization.
-    pub unsafe fn write(&self, i: usize, value: T) {
-        let ptr = self.slice[i].get();
-        *ptr = value;
-    }
+    fn get_name(&self) -> &'static str;
+    fn as_mut_any(&mut self) -> &mut dyn Any;
 }
 
 impl<S, F, const N: usize> NodeTrait<S, N> for Node<S, F, N>
@@ -129,204 +160,58 @@ where
     S: rodio::Sample + Send + Sync + 'static,
     F: Process<S> + Clone,
 {
-    fn get_buf(&self) -> &[S; N] {
-        &self.buf
-    }
-
-    fn stream_into(&mut self, buf: &mut Box<[S; N]>, multithreading: bool) {
-        let num_parents = self.parents.len();
-        let mut data = Vec::with_capacity(num_parents);
-
-        // 1. run the parents nodes first
-        // todo! Handle events that adds a node at runtime!
-        if num_parents > 0 {
-            if multithreading {
-                let (tx, rx) = std::sync::mpsc::channel();
-
-                for parent in self.parents.values_mut() {
-                    let parent = parent.clone();
-                    let tx = tx.clone();
-                    std::thread::spawn(move || {
-                        // Create a buffer on the thread
-                        let mut buffer = unsafe { vec_to_boxed_slice(vec![S::zero_value(); N]) };
-
-                        // Stream into it
-                        parent.lock().unwrap().stream_into(&mut buffer, true);
-
-                        // Send the processed data to the calling thread (receiver)
-                        tx.send(buffer).unwrap();
-                    });
-                }
-                drop(tx);
-
-                while let Ok(buffer) = rx.recv() {
-                    data.push(buffer);
-                }
-            } else {
-                let mut buffer = unsafe { vec_to_boxed_slice(vec![S::zero_value(); N]) };
-
-                for parent in self.parents.values_mut() {
-                    parent.lock().unwrap().stream_into(&mut buffer, false);
-
-                    data.push(buffer.clone());
-                }
-            }
-        }
-
-        let mut input = Vec::with_capacity(data.len());
-        for idx_sample in 0..N {
-            for buf in &data {
-                input.push(buf[idx_sample]);
-            }
-
-            // As events is sorted by decreasing sample indices, we can only check the last event to be played
-            while !self.events.is_empty()
-                && self.events.last().unwrap().get_sample_idx() <= SampleIdx(idx_sample)
-            {
-                let event = self.events.pop().unwrap();
-                event.play_on(self);
-            }
-
-            buf[idx_sample] = if self.on {
-                self.f.process_next_value(&input)
-            } else {
-                S::zero_value()
-            };
-
-            input.clear();
-        }
-    }
-
-    fn stream_into_rtrb(
+    fn process(
         &mut self,
-        multithreading: bool,
-        //pool: &rayon::ThreadPool
+        ctx: &Context,
+        range: Range<usize>,
+        bufs: &[[S; N]],
+        inputs: &[usize],
+        out: &mut [S; N],
     ) {
-        let num_inputs = self.parents.len();
-        let mut data = unsafe { vec_to_boxed_slice::<_, MAX_NODE_INPUTS>(
-            vec![
-                [S::zero_value(); N]; MAX_NODE_INPUTS
-            ])
-        };
-        // 1. run the parents nodes first
-        // todo! Handle events that adds a node at runtime!
-        if num_inputs > 0 {
-            if multithreading {
-                //let mut consumers = vec![];
-                let mut data_slice = UnsafeSlice::new(&mut data[..]);
-
-                rayon::scope(|s| {
-                    for parent in self.parents.values_mut() {
-                        let parent = parent.clone();
-
-                        //consumers.push(c);
-                        s.spawn(move |_| {
-                            let mut input = parent.lock().unwrap();
-                            // Stream into it
-                            input.stream_into_rtrb(true);
-                            let idx = rayon::current_thread_index().unwrap();
-                            // Send the processed data to the calling thread (receiver)
-                            unsafe { data_slice.write(idx, *input.get_buf()); }
-                        });
-                    }
-                });
-            } else {
-                let mut i = 0;
-                for parent in self.parents.values_mut() {
-                    if let Ok(mut parent) = parent.lock() {
-                        parent.stream_into_rtrb(false);
-                        data[i] = *parent.get_buf();
-                        i += 1;
-                    }
-                }
-            }
-        }
+        // The input vector is only reallocated when the number of inputs grows
+        let mut input = std::mem::take(&mut self.input);
+        input.resize(inputs.len(), S::zero_value());
 
-        let mut input = [S::zero_value(); MAX_NODE_INPUTS];
-        for idx_sample in 0..N {
-            for idx_input in 0..num_inputs {
-                input[idx_input] = data[idx_input][idx_sample];
+        for idx_sample in range {
+            for (value, idx_input) in input.iter_mut().zip(inputs) {
+                *value = bufs[*idx_input][idx_sample];
             }
 
-            // As events is sorted by decreasing sample indices, we can only check the last event to be played
-            /*while !self.events.is_empty()
-                && self.events.last().unwrap().get_sample_idx() <= SampleIdx(idx_sample)
-            {
-                let event = self.events.pop().unwrap();
-                event.play_on(self);
-            }*/
-
-            self.buf[idx_sample] = if self.on {
-                self.f.process_next_value(&input[..num_inputs])
-            } else {
-                S::zero_value()
-            };
+            // Events are given in absolute sample indices so they are compared against the graph clock
+            out[idx_sample] = self.process_sample(&ctx.offset(idx_sample), &input);
         }
-    }
-
-    fn collect_nodes(&self, nodes: &mut Nodes<S, N>) {
-        for (name, parent) in self.parents.iter() {
-            nodes.insert(name, parent.clone());
 
-            parent.lock().unwrap().collect_nodes(nodes);
-        }
+        self.input = input;
     }
 
-    fn delete_node(
-        &mut self,
-        name: &'static str,
-        nodes_to_remove: &mut HashSet<&'static str>,
-    ) -> bool {
-        if let Some(node) = self.parents.get(name) {
-            // Node found, we first remove all of its parents (by registering them in a set)
-            node.lock()
-                .unwrap()
-                .delete_parents_hierarchy(nodes_to_remove);
-
-            // Then we remove the node found
-            self.parents.remove(name);
-            // And tag it in the set as well
-            nodes_to_remove.insert(name);
-
-            true
-        } else {
-            // If not found, we loop over the parent hierarchy
-            for parent in self.parents.values_mut() {
-                if parent.lock().unwrap().delete_node(name, nodes_to_remove) {
-                    return true;
-                }
-            }
-
-            false
+    fn prepare(&mut self, sample_rate: f32, max_block: usize) {
+        self.f.prepare(sample_rate, max_block);
+        for parent in self.parents.iter_mut() {
+            parent.prepare(sample_rate, max_block);
         }
+        self.prepared = true;
     }
 
-    fn delete_parents_hierarchy(&mut self, nodes_to_remove: &mut HashSet<&'static str>) {
-        self.parents.retain(|name, parent| {
-            // Delete recursively the parents of the parent node
-            parent
-                .lock()
-                .unwrap()
-                .delete_parents_hierarchy(nodes_to_remove);
-
-            // Then tag the parent to be removed
-            nodes_to_remove.insert(name);
+    fn is_prepared(&self) -> bool {
+        self.prepared
+    }
 
-            // Then remove it in the hierarchy
-            false
-        });
+    fn take_inputs(&mut self) -> Vec<Box<dyn NodeTrait<S, N>>> {
+        std::mem::take(&mut self.parents)
     }
 
-    fn add_input_trait_object(
-        &mut self,
-        name: &'static str,
-        input: Arc<Mutex<dyn NodeTrait<S, N>>>,
-    ) {
-        self.parents.insert(name, input);
+    fn take_graph_events(&mut self, events: &mut Vec<GraphEvent<S, N>>) {
+        // The remaining events stay sorted
+        for event in std::mem::take(&mut self.events) {
+            match event.into_graph_event(self.name) {
+                Ok(event) => events.push(event),
+                Err(event) => self.events.push(event),
+            }
+        }
     }
 
     fn get_name(&self) -> &'static str {
-        &self.name
+        self.name
     }
 
     fn as_mut_any(&mut self) -> &mut dyn Any {
@@ -334,33 +219,38 @@ where
     }
 }
 
-impl<S, F, const N: usize> Iterator for Node<S, F, N>
-where
-    S: rodio::Sample + Send + Sync + 'static,
-    F: Process<S> + Clone,
-{
-    type Item = S;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let in_values: Option<Vec<_>> = self
-            .parents
-            .values_mut()
-            .map(|in_iter| in_iter.lock().unwrap().next())
-            .collect();
-
-        if let Some(values) = in_values {
-            Some(self.f.process_next_value(&values[..]))
-        } else {
-            None
-        }
-    }
-}
-
 pub trait Process<S>: Send
 where
     S: rodio::Sample + Send,
 {
-    fn process_next_value(&mut self, inputs: &[S]) -> S;
+    /// Compute the value of the sample described by `ctx` from the values of the inputs
+    fn process_next_value(&mut self, inputs: &[S], ctx: &Context) -> S;
+
+    /// Called when the node enters a graph, before it processes any sample.
+    /// Buffers depending on the sample rate should be allocated here
+    /// rather than while processing the samples. Until then, a process
+    /// needing buffers passes its first input through
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate of the graph
+    /// * `max_block` - The maximum number of samples processed in a block
+    fn prepare(&mut self, _sample_rate: f32, _max_block: usize) {}
+
+    /// Called when a note on or a note off event is played on the node.
+    /// By default the node is muted between a note off and the next note on,
+    /// processes like envelopes handle the gate themselves instead
+    ///
+    /// # Arguments
+    ///
+    /// * `on` - Whether the note starts or ends
+    ///
+    /// # Return
+    ///
+    /// `true` if the process handled the gate, so that the node is not muted
+    fn gate(&mut self, _on: bool) -> bool {
+        false
+    }
 }
 
 pub mod sinewave;
@@ -369,3 +259,54 @@ pub mod mixer;
 pub use mixer::Mixer;
 pub mod multiplier;
 pub use multiplier::Multiplier;
+pub mod modulation;
+mod polyblep;
+pub use modulation::Modulation;
+pub mod sawwave;
+pub use sawwave::SawWave;
+pub mod squarewave;
+pub use squarewave::SquareWave;
+pub mod trianglewave;
+pub use trianglewave::TriangleWave;
+pub mod pulsewave;
+pub use pulsewave::PulseWave;
+mod rng;
+pub mod whitenoise;
+pub use whitenoise::WhiteNoise;
+pub mod pinknoise;
+pub use pinknoise::PinkNoise;
+pub mod brownnoise;
+pub use brownnoise::BrownNoise;
+pub mod wavetable;
+pub use wavetable::Wavetable;
+pub mod biquad;
+pub use biquad::{Biquad, BiquadKind};
+pub mod statevariablefilter;
+pub use statevariablefilter::StateVariableFilter;
+pub mod ladderfilter;
+pub use ladderfilter::LadderFilter;
+pub mod adsr;
+pub use adsr::{Adsr, AdsrCurve};
+pub mod delay;
+mod delayline;
+pub use delay::{Delay, DelayInterpolation};
+pub mod reverb;
+pub use reverb::Reverb;
+pub mod convolver;
+mod wav;
+pub use convolver::Convolver;
+pub mod compressor;
+mod dynamics;
+pub use compressor::Compressor;
+pub mod limiter;
+pub use limiter::Limiter;
+pub mod expander;
+pub use expander::Expander;
+pub mod gate;
+pub use gate::Gate;
+pub mod chorus;
+pub use chorus::Chorus;
+pub mod flanger;
+pub use flanger::Flanger;
+pub mod phaser;
+pub use phaser::Phaser;
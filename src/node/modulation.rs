@@ -0,0 +1,12 @@
+// How an oscillator uses the values of its inputs, summed together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modulation {
+    // The inputs are ignored
+    None,
+    // The frequency is multiplied by 1 + index * input. It does not go below 0 Hz
+    LinearFm,
+    // Like LinearFm, but the phase runs backward when the frequency goes below 0 Hz
+    ThroughZeroFm,
+    // The phase is moved by index * input radians
+    Pm,
+}
@@ -0,0 +1,81 @@
+use super::polyblep::Phase;
+
+// Phaser: the input mixed with itself through a chain of allpass filters whose
+// frequency is swept by an LFO, making notches move along the spectrum
+//
+// The first input is processed.
+#[derive(Clone)]
+pub struct Phaser {
+    pub params: PhaserParams,
+    // States of the allpass filters
+    stages: [f32; NUM_STAGES],
+    // Last output of the chain, fed back into it
+    last: f32,
+    lfo: Phase,
+}
+
+#[derive(Clone)]
+pub struct PhaserParams {
+    // Frequency of the LFO in Hz
+    pub rate: f32,
+    // Between 0 and 1, how much of SWEEP octaves the allpass filters move above `freq`
+    pub depth: f32,
+    // Lowest frequency of the allpass filters in Hz
+    pub freq: f32,
+    // Part of the output of the chain fed back into it, between -1 and 1
+    pub feedback: f32,
+    // Part of the output of the chain in the output, the rest being the input
+    pub mix: f32,
+}
+
+// With three pairs of stages, the mix has three notches
+const NUM_STAGES: usize = 6;
+const SWEEP: f32 = 4.0;
+
+impl Phaser {
+    pub fn new(rate: f32, depth: f32) -> Self {
+        let params = PhaserParams {
+            rate,
+            depth,
+            freq: 200.0,
+            feedback: 0.0,
+            mix: 0.5,
+        };
+
+        Self {
+            params,
+            stages: [0.0; NUM_STAGES],
+            last: 0.0,
+            lfo: Phase::default(),
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Phaser {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+
+        // The frequency goes from `freq` up and back down
+        let (t, _) = self.lfo.next(self.params.rate, ctx.sample_rate);
+        let lfo = 0.5 - 0.5 * (t * std::f64::consts::TAU).cos() as f32;
+        let depth = self.params.depth.clamp(0.0, 1.0);
+        let freq = self.params.freq * (depth * SWEEP * lfo).exp2();
+        let freq = freq.clamp(1.0, 0.49 * ctx.sample_rate);
+
+        // First order allpass filters with a phase shift of 90 degrees at `freq`
+        let g = (std::f32::consts::PI * freq / ctx.sample_rate).tan();
+        let a = (g - 1.0) / (g + 1.0);
+
+        let feedback = self.params.feedback.clamp(-0.99, 0.99);
+        let mut x = input + feedback * self.last;
+        for state in self.stages.iter_mut() {
+            let y = a * x + *state;
+            *state = x - a * y;
+            x = y;
+        }
+        self.last = x;
+
+        (1.0 - self.params.mix) * input + self.params.mix * x
+    }
+}
@@ -0,0 +1,49 @@
+use super::rng::Rng;
+
+// Noise whose power decreases by 3 dB per octave
+#[derive(Clone)]
+pub struct PinkNoise {
+    pub params: PinkNoiseParams,
+    rng: Rng,
+    // States of the filters shaping the white noise
+    b: [f32; 7],
+}
+
+#[derive(Clone)]
+pub struct PinkNoiseParams {
+    pub ampl: f32,
+}
+
+impl PinkNoise {
+    // The same seed always gives the same noise
+    pub fn new(ampl: f32, seed: u64) -> Self {
+        let params = PinkNoiseParams { ampl };
+        let rng = Rng::new(seed);
+        Self {
+            params,
+            rng,
+            b: [0.0; 7],
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for PinkNoise {
+    fn process_next_value(&mut self, _: &[f32], _: &Context) -> f32 {
+        let white = self.rng.next_f32();
+
+        // Paul Kellet's filter, accurate to +/- 0.05 dB above 9.2 Hz at 44100 Hz
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+
+        // The filter has a gain of about 5
+        pink * 0.2 * self.params.ampl
+    }
+}
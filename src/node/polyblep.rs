@@ -0,0 +1,85 @@
+// Polynomial corrections removing the aliasing of the discontinuities of the naive waveforms
+//
+// `t` is the position in the cycle, between 0 and 1, and `dt` the phase increment
+// of a sample. The discontinuity is at `t` = 0, the correction spreads over
+// the sample before and the sample after it.
+
+// Correction of a step going up by 2, to be added to the naive waveform
+pub(crate) fn poly_blep(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let x = t / dt;
+        2.0 * x - x * x - 1.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        x * x + 2.0 * x + 1.0
+    } else {
+        0.0
+    }
+}
+
+// Correction of a change of slope of 1 per sample, to be added to the naive waveform
+pub(crate) fn poly_blamp(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let x = 1.0 - t / dt;
+        x * x * x / 6.0
+    } else if t > 1.0 - dt {
+        let x = 1.0 + (t - 1.0) / dt;
+        x * x * x / 6.0
+    } else {
+        0.0
+    }
+}
+
+// Band-limited pulse being 1 during the first `width` part of the cycle and -1 after
+pub(crate) fn pulse(t: f64, dt: f64, width: f64) -> f64 {
+    let naive = if t < width { 1.0 } else { -1.0 };
+    let t_down = (t - width + 1.0).fract();
+
+    naive + poly_blep(t, dt) - poly_blep(t_down, dt)
+}
+
+use super::Modulation;
+
+// Position in the cycle of an oscillator, accumulated in double precision so that it does not drift
+#[derive(Clone, Default)]
+pub(crate) struct Phase(f64);
+
+impl Phase {
+    // Give the current position and the phase increment, then move to the next sample
+    pub(crate) fn next(&mut self, freq: f32, sample_rate: f32) -> (f64, f64) {
+        let t = self.0;
+        let dt = (freq as f64 / sample_rate as f64).abs();
+
+        self.0 += freq as f64 / sample_rate as f64;
+        self.0 -= self.0.floor();
+
+        (t, dt)
+    }
+
+    // Like `next` for an oscillator modulated by the sum of `inputs`
+    pub(crate) fn next_modulated(
+        &mut self,
+        freq: f32,
+        sample_rate: f32,
+        modulation: Modulation,
+        index: f32,
+        inputs: &[f32],
+    ) -> (f64, f64) {
+        let input = inputs.iter().sum::<f32>();
+        // The modulated frequency stays below the Nyquist frequency, whatever the modulator
+        let nyquist = sample_rate / 2.0;
+        let modulated = freq * (1.0 + index * input);
+        let modulated = if modulated.is_nan() { 0.0 } else { modulated };
+
+        match modulation {
+            Modulation::None => self.next(freq, sample_rate),
+            Modulation::LinearFm => self.next(modulated.clamp(0.0, nyquist), sample_rate),
+            Modulation::ThroughZeroFm => self.next(modulated.clamp(-nyquist, nyquist), sample_rate),
+            Modulation::Pm => {
+                let (t, dt) = self.next(freq, sample_rate);
+                let offset = (index * input) as f64 / std::f64::consts::TAU;
+                ((t + offset).rem_euclid(1.0), dt)
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use super::polyblep::{pulse, Phase};
+
+// Band-limited pulse wave, at ampl during the first `pulse_width` part of the cycle and -ampl after
+#[derive(Clone)]
+pub struct PulseWave {
+    pub params: PulseWaveParams,
+    phase: Phase,
+}
+
+#[derive(Clone)]
+pub struct PulseWaveParams {
+    pub ampl: f32,
+    // Frequency in Hz
+    pub freq: f32,
+    // Part of the cycle spent up, between 0 and 1
+    pub pulse_width: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
+}
+
+impl PulseWave {
+    pub fn new(ampl: f32, freq: f32, pulse_width: f32) -> Self {
+        let params = PulseWaveParams {
+            ampl,
+            freq,
+            pulse_width,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+        let phase = Phase::default();
+        Self { params, phase }
+    }
+}
+
+use super::{Context, Modulation, Process};
+impl Process<f32> for PulseWave {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (t, dt) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        // Both edges need a sample on each side to be corrected. Above the Nyquist
+        // frequency there is no such width, the pulse is then a square
+        let dt = dt.min(0.5);
+        let width = (self.params.pulse_width as f64).max(dt).min(1.0 - dt);
+
+        pulse(t, dt, width) as f32 * self.params.ampl
+    }
+}
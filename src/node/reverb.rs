@@ -0,0 +1,141 @@
+// Freeverb, Jezar's reverberator: eight damped comb filters in parallel followed by
+// four allpass filters in series
+//
+// The first input is reverberated. The buffers are allocated when the node enters a
+// graph, their lengths depending on the sample rate.
+#[derive(Clone)]
+pub struct Reverb {
+    pub params: ReverbParams,
+    pre_delay: Vec<f32>,
+    idx_pre_delay: usize,
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+#[derive(Clone)]
+pub struct ReverbParams {
+    // Between 0 and 1, the larger the room the longer the decay
+    pub room_size: f32,
+    // Between 0 and 1, how faster the high frequencies decay
+    pub damping: f32,
+    // Time before the reverberation starts in seconds, up to MAX_PRE_DELAY
+    pub pre_delay: f32,
+    // Part of the reverberation in the output, the rest being the input
+    pub mix: f32,
+}
+
+#[derive(Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    idx: usize,
+    // State of the low-pass filter of the feedback
+    damped: f32,
+}
+
+#[derive(Clone)]
+struct Allpass {
+    buffer: Vec<f32>,
+    idx: usize,
+}
+
+// Longest pre-delay in seconds
+const MAX_PRE_DELAY: f32 = 0.5;
+
+// Lengths of the filters at 44100 Hz
+const COMB_LENGTHS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_LENGTHS: [usize; 4] = [556, 441, 341, 225];
+const ALLPASS_FEEDBACK: f32 = 0.5;
+// Gains of the input of the combs and of the sum of their outputs
+const INPUT_GAIN: f32 = 0.015;
+const WET_GAIN: f32 = 3.0;
+
+impl Reverb {
+    pub fn new(room_size: f32, damping: f32) -> Self {
+        let params = ReverbParams {
+            room_size,
+            damping,
+            pre_delay: 0.0,
+            mix: 0.3,
+        };
+
+        Self {
+            params,
+            pre_delay: vec![],
+            idx_pre_delay: 0,
+            combs: vec![],
+            allpasses: vec![],
+        }
+    }
+
+    // Time in seconds for the reverberation to decay by 60 dB without damping,
+    // averaged over the combs. It does not depend on the sample rate
+    pub fn rt60(&self) -> f32 {
+        let num_repeats = -3.0 / self.feedback().log10();
+        let mean_len = COMB_LENGTHS.iter().sum::<usize>() as f32 / COMB_LENGTHS.len() as f32;
+
+        num_repeats * mean_len / 44100.0
+    }
+
+    fn feedback(&self) -> f32 {
+        0.7 + 0.28 * self.params.room_size.clamp(0.0, 1.0)
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for Reverb {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        if self.pre_delay.is_empty() {
+            return input;
+        }
+
+        // The pre-delay line is read before being written so that a pre-delay of 0 is one sample
+        let len = self.pre_delay.len();
+        let delay = ((self.params.pre_delay * ctx.sample_rate) as usize).clamp(1, len - 1);
+        let delayed = self.pre_delay[(self.idx_pre_delay + len - delay) % len];
+        self.pre_delay[self.idx_pre_delay] = input;
+        self.idx_pre_delay = (self.idx_pre_delay + 1) % len;
+
+        let feedback = self.feedback();
+        let damping = 0.4 * self.params.damping.clamp(0.0, 1.0);
+        let mut wet = 0.0;
+        for comb in self.combs.iter_mut() {
+            let out = comb.buffer[comb.idx];
+            comb.damped = out * (1.0 - damping) + comb.damped * damping;
+            comb.buffer[comb.idx] = delayed * INPUT_GAIN + comb.damped * feedback;
+            comb.idx = (comb.idx + 1) % comb.buffer.len();
+            wet += out;
+        }
+
+        for allpass in self.allpasses.iter_mut() {
+            let out = allpass.buffer[allpass.idx];
+            allpass.buffer[allpass.idx] = wet + out * ALLPASS_FEEDBACK;
+            allpass.idx = (allpass.idx + 1) % allpass.buffer.len();
+            wet = out - wet;
+        }
+
+        (1.0 - self.params.mix) * input + self.params.mix * WET_GAIN * wet
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block: usize) {
+        let scale = |len: usize| ((len as f32 * sample_rate / 44100.0) as usize).max(1);
+
+        self.pre_delay = vec![0.0; (MAX_PRE_DELAY * sample_rate) as usize + 2];
+        self.idx_pre_delay = 0;
+        self.combs = COMB_LENGTHS
+            .iter()
+            .map(|len| Comb {
+                buffer: vec![0.0; scale(*len)],
+                idx: 0,
+                damped: 0.0,
+            })
+            .collect();
+        self.allpasses = ALLPASS_LENGTHS
+            .iter()
+            .map(|len| Allpass {
+                buffer: vec![0.0; scale(*len)],
+                idx: 0,
+            })
+            .collect();
+    }
+}
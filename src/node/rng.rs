@@ -0,0 +1,27 @@
+// Pseudo random number generator of the noise nodes (SplitMix64)
+//
+// It does not allocate and gives the same sequence for the same seed
+// so that offline renders are reproducible.
+#[derive(Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniformly distributed between -1 and 1
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        // The 24 upper bits fit exactly in the mantissa of a f32
+        let value = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        2.0 * value - 1.0
+    }
+}
@@ -0,0 +1,50 @@
+use super::polyblep::{poly_blep, Phase};
+
+// Band-limited sawtooth going up from -ampl to ampl
+#[derive(Clone)]
+pub struct SawWave {
+    pub params: SawWaveParams,
+    phase: Phase,
+}
+
+#[derive(Clone)]
+pub struct SawWaveParams {
+    pub ampl: f32,
+    // Frequency in Hz
+    pub freq: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
+}
+
+impl SawWave {
+    pub fn new(ampl: f32, freq: f32) -> Self {
+        let params = SawWaveParams {
+            ampl,
+            freq,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+        let phase = Phase::default();
+        Self { params, phase }
+    }
+}
+
+use super::{Context, Modulation, Process};
+impl Process<f32> for SawWave {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (t, dt) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        // The sawtooth falls by 2 at the start of a cycle
+        let value = 2.0 * t - 1.0 - poly_blep(t, dt);
+
+        value as f32 * self.params.ampl
+    }
+}
@@ -1,27 +1,47 @@
+use super::polyblep::Phase;
+
 #[derive(Clone)]
 pub struct SineWave {
     pub params: SineWaveParams,
-    step: usize,
+    phase: Phase,
 }
 
 #[derive(Clone)]
 pub struct SineWaveParams {
     pub ampl: f32,
+    // Frequency in Hz
     pub freq: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
 }
 
 impl SineWave {
     pub fn new(ampl: f32, freq: f32) -> Self {
-        let params = SineWaveParams { ampl, freq };
-        let step = 0;
-        Self { params, step }
+        let params = SineWaveParams {
+            ampl,
+            freq,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+        let phase = Phase::default();
+        Self { params, phase }
     }
 }
 
-use super::Process;
+use super::{Context, Modulation, Process};
 impl Process<f32> for SineWave {
-    fn process_next_value(&mut self, _: &[f32]) -> f32 {
-        self.step += 1;
-        ((self.step as f32) / 44100.0 * self.params.freq).sin() * self.params.ampl
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        // The phase is accumulated so that a change of frequency does not make it jump
+        let (t, _) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        (t * std::f64::consts::TAU).sin() as f32 * self.params.ampl
     }
 }
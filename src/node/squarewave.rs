@@ -0,0 +1,47 @@
+use super::polyblep::{pulse, Phase};
+
+// Band-limited square wave, at ampl during the first half of the cycle and -ampl after
+#[derive(Clone)]
+pub struct SquareWave {
+    pub params: SquareWaveParams,
+    phase: Phase,
+}
+
+#[derive(Clone)]
+pub struct SquareWaveParams {
+    pub ampl: f32,
+    // Frequency in Hz
+    pub freq: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
+}
+
+impl SquareWave {
+    pub fn new(ampl: f32, freq: f32) -> Self {
+        let params = SquareWaveParams {
+            ampl,
+            freq,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+        let phase = Phase::default();
+        Self { params, phase }
+    }
+}
+
+use super::{Context, Modulation, Process};
+impl Process<f32> for SquareWave {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (t, dt) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        pulse(t, dt, 0.5) as f32 * self.params.ampl
+    }
+}
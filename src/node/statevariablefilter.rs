@@ -0,0 +1,93 @@
+// Zero-delay feedback state variable filter, after Andrew Simper's trapezoidal SVF
+//
+// The low-pass, band-pass and high-pass responses are computed together and the output
+// of the node is their sum weighted by `low`, `band` and `high`, so that a notch is
+// `low = high = 1` for instance. The first input is filtered and the second one, if
+// any, moves the cutoff by `depth` octaves per unit.
+#[derive(Clone)]
+pub struct StateVariableFilter {
+    pub params: StateVariableFilterParams,
+    // The states of the two integrators
+    ic1eq: f64,
+    ic2eq: f64,
+}
+
+#[derive(Clone)]
+pub struct StateVariableFilterParams {
+    // Cutoff frequency in Hz
+    pub cutoff: f32,
+    // Resonance between 0 and 1
+    pub resonance: f32,
+    // Modulation of the cutoff by the second input, in octaves
+    pub depth: f32,
+    // Gains of the responses in the output
+    pub low: f32,
+    pub band: f32,
+    pub high: f32,
+}
+
+// The responses of the filter for a sample
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvfOutputs {
+    pub low: f32,
+    pub band: f32,
+    pub high: f32,
+}
+
+impl StateVariableFilter {
+    // A low-pass filter. The other responses are added by changing the gains of the params
+    pub fn new(cutoff: f32, resonance: f32) -> Self {
+        let params = StateVariableFilterParams {
+            cutoff,
+            resonance,
+            depth: 1.0,
+            low: 1.0,
+            band: 0.0,
+            high: 0.0,
+        };
+
+        Self {
+            params,
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        }
+    }
+
+    // Filter a sample with the given cutoff and get the three responses
+    pub fn tick(&mut self, input: f32, cutoff: f32, sample_rate: f32) -> SvfOutputs {
+        let cutoff = cutoff.clamp(1.0, 0.49 * sample_rate) as f64;
+        let g = (std::f64::consts::PI * cutoff / sample_rate as f64).tan();
+        // The damping never reaches 0 so that the filter rings down at maximum resonance
+        let k = 2.0 * (1.0 - self.params.resonance.clamp(0.0, 1.0) as f64).max(0.005);
+
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v0 = input as f64;
+        let v3 = v0 - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        SvfOutputs {
+            low: v2 as f32,
+            band: v1 as f32,
+            high: (v0 - k * v1 - v2) as f32,
+        }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for StateVariableFilter {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let input = inputs.first().copied().unwrap_or(0.0);
+        let modulation = inputs.get(1).copied().unwrap_or(0.0);
+        let cutoff = self.params.cutoff * (self.params.depth * modulation).exp2();
+
+        let out = self.tick(input, cutoff, ctx.sample_rate);
+
+        self.params.low * out.low + self.params.band * out.band + self.params.high * out.high
+    }
+}
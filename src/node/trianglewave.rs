@@ -0,0 +1,53 @@
+use super::polyblep::{poly_blamp, Phase};
+
+// Band-limited triangle wave, at ampl at the start of the cycle and -ampl at its middle
+#[derive(Clone)]
+pub struct TriangleWave {
+    pub params: TriangleWaveParams,
+    phase: Phase,
+}
+
+#[derive(Clone)]
+pub struct TriangleWaveParams {
+    pub ampl: f32,
+    // Frequency in Hz
+    pub freq: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
+}
+
+impl TriangleWave {
+    pub fn new(ampl: f32, freq: f32) -> Self {
+        let params = TriangleWaveParams {
+            ampl,
+            freq,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+        let phase = Phase::default();
+        Self { params, phase }
+    }
+}
+
+use super::{Context, Modulation, Process};
+impl Process<f32> for TriangleWave {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (t, dt) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        let naive = 4.0 * (t - 0.5).abs() - 1.0;
+        // The slope goes from 4 to -4 per cycle at the start of the cycle and back at its middle
+        let slope_change = 8.0 * dt;
+        let value = naive - slope_change * poly_blamp(t, dt)
+            + slope_change * poly_blamp((t + 0.5).fract(), dt);
+
+        value as f32 * self.params.ampl
+    }
+}
@@ -0,0 +1,11 @@
+// Read the samples of the first channel of a WAV file
+pub(crate) fn read_first_channel<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Vec<f32>> {
+    use rodio::Source;
+    use std::io::{Error, ErrorKind};
+
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let decoder = rodio::Decoder::new(file).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let channels = decoder.channels() as usize;
+
+    Ok(decoder.convert_samples::<f32>().step_by(channels).collect())
+}
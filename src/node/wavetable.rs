@@ -0,0 +1,218 @@
+use super::polyblep::Phase;
+use crate::fft::{Complex, Fft};
+
+use std::sync::Arc;
+
+// Number of samples of a cycle in the tables
+const TABLE_LEN: usize = 2048;
+// Number of band-limited tables of a frame. The table of level `l` holds the
+// harmonics up to `TABLE_LEN / 2 >> l`, down to the fundamental only
+const NUM_LEVELS: usize = TABLE_LEN.trailing_zeros() as usize;
+
+// Oscillator playing single-cycle waveforms
+//
+// A wavetable is made of several frames. The `position` parameter crossfades between
+// them, from the first frame at 0 to the last one at 1. Each frame is stored at several
+// levels of band-limitation and the level with no harmonic above the Nyquist
+// frequency is played, so that the wavetable does not alias at high pitches.
+#[derive(Clone)]
+pub struct Wavetable {
+    pub params: WavetableParams,
+    // The tables are shared by the clones of the node
+    tables: Arc<Tables>,
+    phase: Phase,
+}
+
+#[derive(Clone)]
+pub struct WavetableParams {
+    pub ampl: f32,
+    // Frequency in Hz
+    pub freq: f32,
+    // Position in the frames, between 0 and 1
+    pub position: f32,
+    // How the inputs modulate the oscillator
+    pub modulation: Modulation,
+    // Depth of the modulation
+    pub index: f32,
+}
+
+// For each frame, the tables of each level
+struct Tables(Vec<Vec<[f32; TABLE_LEN]>>);
+
+impl Wavetable {
+    // Build the frames from the amplitudes of their harmonics.
+    // The first amplitude of a frame is the one of its fundamental
+    pub fn from_harmonics(ampl: f32, freq: f32, frames: &[Vec<f32>]) -> Self {
+        let spectra = frames
+            .iter()
+            .map(|amplitudes| {
+                let mut spectrum = vec![Complex::default(); TABLE_LEN / 2];
+                // A sine of amplitude a is given by the bins -ia/2 and ia/2 of its positive and negative frequencies
+                for (harmonic, ampl) in amplitudes.iter().take(TABLE_LEN / 2 - 1).enumerate() {
+                    spectrum[harmonic + 1] = Complex::new(0.0, -ampl / 2.0) * TABLE_LEN as f32;
+                }
+                spectrum
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_spectra(ampl, freq, spectra)
+    }
+
+    // Build the frames from the samples of a cycle. The frames can be of any length
+    pub fn from_frames(ampl: f32, freq: f32, frames: &[Vec<f32>]) -> Self {
+        let spectra = frames.iter().map(|frame| spectrum(frame)).collect();
+
+        Self::from_spectra(ampl, freq, spectra)
+    }
+
+    // Load the frames from a WAV file made of cycles of `frame_len` samples one after the other.
+    // Only the first channel is read
+    pub fn from_wav<P: AsRef<std::path::Path>>(
+        path: P,
+        frame_len: usize,
+        ampl: f32,
+        freq: f32,
+    ) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+
+        let samples = super::wav::read_first_channel(path)?;
+
+        if frame_len == 0 || samples.len() < frame_len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the file does not hold a whole frame",
+            ));
+        }
+
+        let frames = samples
+            .chunks_exact(frame_len)
+            .map(|frame| frame.to_vec())
+            .collect::<Vec<_>>();
+
+        Ok(Self::from_frames(ampl, freq, &frames))
+    }
+
+    fn from_spectra(ampl: f32, freq: f32, spectra: Vec<Vec<Complex>>) -> Self {
+        assert!(!spectra.is_empty(), "a wavetable needs at least one frame");
+
+        let fft = Fft::new(TABLE_LEN);
+        let mut buf = vec![Complex::default(); TABLE_LEN];
+
+        let tables = spectra
+            .iter()
+            .map(|spectrum| {
+                (0..NUM_LEVELS)
+                    .map(|level| {
+                        // Keep the harmonics of the level, without the DC
+                        let max_harmonic = ((TABLE_LEN / 2) >> level).min(TABLE_LEN / 2 - 1);
+                        buf.fill(Complex::default());
+                        for (harmonic, bin) in spectrum.iter().enumerate().skip(1) {
+                            if harmonic > max_harmonic {
+                                break;
+                            }
+                            buf[harmonic] = *bin;
+                            buf[TABLE_LEN - harmonic] = bin.conj();
+                        }
+                        fft.inverse(&mut buf);
+
+                        let mut table = [0.0; TABLE_LEN];
+                        for (value, bin) in table.iter_mut().zip(buf.iter()) {
+                            *value = bin.re;
+                        }
+                        table
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let params = WavetableParams {
+            ampl,
+            freq,
+            position: 0.0,
+            modulation: Modulation::None,
+            index: 1.0,
+        };
+
+        Self {
+            params,
+            tables: Arc::new(Tables(tables)),
+            phase: Phase::default(),
+        }
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.tables.0.len()
+    }
+}
+
+// The bins of the harmonics of a cycle, scaled to a cycle of TABLE_LEN samples
+fn spectrum(frame: &[f32]) -> Vec<Complex> {
+    let len = frame.len();
+    let num_harmonics = (len / 2).min(TABLE_LEN / 2);
+    let scale = TABLE_LEN as f32 / len as f32;
+
+    let bins = if len.is_power_of_two() {
+        let mut buf = frame
+            .iter()
+            .map(|s| Complex::new(*s, 0.0))
+            .collect::<Vec<_>>();
+        Fft::new(len).forward(&mut buf);
+        buf.truncate(num_harmonics);
+        buf
+    } else {
+        (0..num_harmonics)
+            .map(|harmonic| {
+                frame
+                    .iter()
+                    .enumerate()
+                    .fold(Complex::default(), |bin, (idx, s)| {
+                        let angle = -std::f64::consts::TAU * (harmonic * idx) as f64 / len as f64;
+                        bin + Complex::new(angle.cos() as f32, angle.sin() as f32) * *s
+                    })
+            })
+            .collect()
+    };
+
+    bins.into_iter().map(|bin| bin * scale).collect()
+}
+
+// Value of a table at the position `t` in the cycle, linearly interpolated
+fn read(table: &[f32; TABLE_LEN], t: f64) -> f32 {
+    let pos = t * TABLE_LEN as f64;
+    let idx = pos as usize % TABLE_LEN;
+    let frac = pos.fract() as f32;
+
+    table[idx] + frac * (table[(idx + 1) % TABLE_LEN] - table[idx])
+}
+
+use super::{Context, Modulation, Process};
+impl Process<f32> for Wavetable {
+    fn process_next_value(&mut self, inputs: &[f32], ctx: &Context) -> f32 {
+        let (t, dt) = self.phase.next_modulated(
+            self.params.freq,
+            ctx.sample_rate,
+            self.params.modulation,
+            self.params.index,
+            inputs,
+        );
+
+        // The first level whose harmonics are all below the Nyquist frequency,
+        // for the frequency given by the modulation
+        let num_harmonics = 0.5 / dt;
+        let level = ((TABLE_LEN / 2) as f64 / num_harmonics).log2().ceil();
+        let level = (level.max(0.0) as usize).min(NUM_LEVELS - 1);
+
+        let frames = &self.tables.0;
+        let position = self.params.position.clamp(0.0, 1.0) * (frames.len() - 1) as f32;
+        let frame = (position as usize).min(frames.len() - 1);
+        let frac = position - frame as f32;
+
+        let mut value = read(&frames[frame][level], t);
+        if frac > 0.0 {
+            let next = read(&frames[frame + 1][level], t);
+            value += frac * (next - value);
+        }
+
+        value * self.params.ampl
+    }
+}
@@ -0,0 +1,29 @@
+use super::rng::Rng;
+
+// Noise with the same power at all the frequencies
+#[derive(Clone)]
+pub struct WhiteNoise {
+    pub params: WhiteNoiseParams,
+    rng: Rng,
+}
+
+#[derive(Clone)]
+pub struct WhiteNoiseParams {
+    pub ampl: f32,
+}
+
+impl WhiteNoise {
+    // The same seed always gives the same noise
+    pub fn new(ampl: f32, seed: u64) -> Self {
+        let params = WhiteNoiseParams { ampl };
+        let rng = Rng::new(seed);
+        Self { params, rng }
+    }
+}
+
+use super::{Context, Process};
+impl Process<f32> for WhiteNoise {
+    fn process_next_value(&mut self, _: &[f32], _: &Context) -> f32 {
+        self.rng.next_f32() * self.params.ampl
+    }
+}
@@ -0,0 +1,412 @@
+use crate::context::Context;
+use crate::event::GraphEvent;
+use crate::node::NodeTrait;
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// The nodes of a graph compiled into a flat list of nodes sorted in topological order
+//
+// The inputs of a node are always placed before it so that the graph is rendered by
+// processing the nodes one after the other. Each node writes into its own preallocated
+// buffer which is then read by all the nodes it is an input of. A node feeding
+// several nodes is therefore processed only once.
+//
+// The nodes are grouped by level: the level of a node is the length of the longest
+// path from a source to it. Nodes of the same level do not depend on each other
+// so that they can be processed in parallel once the previous levels are done.
+//
+// The plan is compiled again only when the topology of the graph changes.
+pub(crate) struct RenderPlan<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    // The nodes sorted in topological order
+    nodes: Vec<Box<dyn NodeTrait<S, N>>>,
+    // For each node, the indices of its inputs in `nodes`
+    inputs: Vec<Vec<usize>>,
+    // For each node, the buffer it streams into
+    bufs: Vec<[S; N]>,
+    // Index of the nodes by their name. The root does not belong to it
+    names: HashMap<&'static str, usize>,
+    // Index of the root node
+    root: usize,
+    // Ranges of the indices of the nodes of each level
+    levels: Vec<Range<usize>>,
+    // Nodes dropped from the plan, waiting to be deallocated
+    removed: Vec<Box<dyn NodeTrait<S, N>>>,
+    // Sample rate the nodes are prepared for
+    sample_rate: f32,
+    // Storage of the compilation, kept so that it is not allocated at each change of the graph
+    scratch: Scratch<S, N>,
+}
+
+struct Scratch<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    // The nodes in the order of the search, then in topological order
+    order: Vec<usize>,
+    visited: Vec<bool>,
+    level: Vec<usize>,
+    // For each node, its position in the search, then its index in the compiled plan
+    new_idx: Vec<usize>,
+    // The nodes, their inputs and their buffers before they are moved at their new indices
+    nodes: Vec<Option<Box<dyn NodeTrait<S, N>>>>,
+    inputs: Vec<Vec<usize>>,
+    bufs: Vec<[S; N]>,
+}
+
+impl<S, const N: usize> Scratch<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    fn new() -> Self {
+        Self {
+            order: vec![],
+            visited: vec![],
+            level: vec![],
+            new_idx: vec![],
+            nodes: vec![],
+            inputs: vec![],
+            bufs: vec![],
+        }
+    }
+}
+
+impl<S, const N: usize> RenderPlan<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    // Create the plan of the graph whose root is `root`
+    //
+    // The events changing the topology registered on the nodes are moved to `events`
+    pub(crate) fn new(
+        root: Box<dyn NodeTrait<S, N>>,
+        sample_rate: f32,
+        events: &mut Vec<GraphEvent<S, N>>,
+    ) -> Self {
+        let mut plan = Self {
+            nodes: vec![],
+            inputs: vec![],
+            bufs: vec![],
+            names: HashMap::new(),
+            root: 0,
+            levels: vec![],
+            removed: vec![],
+            sample_rate,
+            scratch: Scratch::new(),
+        };
+
+        plan.root = plan.insert(root, events);
+        plan.compile();
+
+        plan
+    }
+
+    pub(crate) fn get_mut(&mut self, name: &'static str) -> Option<&mut Box<dyn NodeTrait<S, N>>> {
+        let idx = *self.names.get(name)?;
+        Some(&mut self.nodes[idx])
+    }
+
+    // Add the node `input`, with its own inputs, as an input of the node `name`
+    pub(crate) fn add_input(
+        &mut self,
+        name: &'static str,
+        input: Box<dyn NodeTrait<S, N>>,
+        events: &mut Vec<GraphEvent<S, N>>,
+    ) -> bool {
+        if let Some(&idx) = self.names.get(name) {
+            let idx_input = self.insert(input, events);
+            self.inputs[idx].push(idx_input);
+
+            self.compile();
+
+            true
+        } else {
+            // The node is deallocated with the removed ones
+            self.removed.push(input);
+            false
+        }
+    }
+
+    // Remove the input `input_name` of the node `name`
+    //
+    // The removed node and its inputs are dropped if they do not feed other nodes
+    pub(crate) fn remove_input(&mut self, name: &'static str, input_name: &'static str) -> bool {
+        if let (Some(&idx), Some(&idx_input)) = (self.names.get(name), self.names.get(input_name)) {
+            let num_inputs = self.inputs[idx].len();
+            self.inputs[idx].retain(|i| *i != idx_input);
+
+            if self.inputs[idx].len() < num_inputs {
+                self.compile();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Delete the node `name` from the graph
+    //
+    // The inputs of the removed node are dropped as well if they do not feed other nodes
+    pub(crate) fn delete_node(&mut self, name: &'static str) -> bool {
+        if let Some(&idx) = self.names.get(name) {
+            for inputs in self.inputs.iter_mut() {
+                inputs.retain(|i| *i != idx);
+            }
+
+            self.compile();
+
+            true
+        } else {
+            false
+        }
+    }
+
+    // Add the node `src` as an input of the node `dst`
+    //
+    // The connection is rejected if it already exists or if it creates a cycle
+    pub(crate) fn connect(&mut self, src: &'static str, dst: &'static str) -> bool {
+        let (idx_src, idx_dst) = match (self.names.get(src), self.names.get(dst)) {
+            (Some(&idx_src), Some(&idx_dst)) => (idx_src, idx_dst),
+            _ => return false,
+        };
+
+        // `dst` cannot be `src` itself or one of its inputs
+        if self.depends_on(idx_src, idx_dst) || self.inputs[idx_dst].contains(&idx_src) {
+            return false;
+        }
+
+        self.inputs[idx_dst].push(idx_src);
+
+        self.compile();
+
+        true
+    }
+
+    // Move the node `name` from the inputs of `from` to the inputs of `to`
+    pub(crate) fn reconnect(
+        &mut self,
+        name: &'static str,
+        from: &'static str,
+        to: &'static str,
+    ) -> bool {
+        let (idx, idx_from, idx_to) = match (
+            self.names.get(name),
+            self.names.get(from),
+            self.names.get(to),
+        ) {
+            (Some(&idx), Some(&idx_from), Some(&idx_to)) => (idx, idx_from, idx_to),
+            _ => return false,
+        };
+
+        // `to` cannot be the node itself or one of its inputs, this would create a cycle
+        if self.depends_on(idx, idx_to)
+            || !self.inputs[idx_from].contains(&idx)
+            || self.inputs[idx_to].contains(&idx)
+        {
+            return false;
+        }
+
+        self.inputs[idx_from].retain(|i| *i != idx);
+        self.inputs[idx_to].push(idx);
+
+        self.compile();
+
+        true
+    }
+
+    // Stream the samples of the block whose context is `ctx` and whose indices are in `range`
+    //
+    // If a thread pool is given, the nodes of a same level are processed in parallel on it
+    pub(crate) fn render(
+        &mut self,
+        ctx: &Context,
+        range: Range<usize>,
+        pool: Option<&rayon::ThreadPool>,
+    ) {
+        for level in self.levels.iter() {
+            // The inputs of the nodes of a level are in the previous levels
+            let (bufs, outs) = self.bufs.split_at_mut(level.start);
+            let outs = &mut outs[..level.len()];
+            let nodes = &mut self.nodes[level.clone()];
+            let inputs = &self.inputs[level.clone()];
+
+            match pool {
+                Some(pool) if level.len() > 1 => pool.install(|| {
+                    nodes
+                        .par_iter_mut()
+                        .zip(outs.par_iter_mut())
+                        .zip(inputs.par_iter())
+                        .for_each(|((node, out), inputs)| {
+                            node.process(ctx, range.clone(), bufs, inputs, out)
+                        })
+                }),
+                _ => {
+                    for ((node, out), inputs) in nodes.iter_mut().zip(outs).zip(inputs) {
+                        node.process(ctx, range.clone(), bufs, inputs, out);
+                    }
+                }
+            }
+        }
+    }
+
+    // The nodes dropped from the plan since the last call.
+    // They are deallocated by the caller, possibly on another thread
+    pub(crate) fn removed(&mut self) -> &mut Vec<Box<dyn NodeTrait<S, N>>> {
+        &mut self.removed
+    }
+
+    // The buffer of the root node
+    pub(crate) fn output(&self) -> &[S; N] {
+        &self.bufs[self.root]
+    }
+
+    // Push the node and its inputs at the end of the plan, preparing them if they are not yet.
+    // Return the index of the node. The plan has to be compiled after that
+    fn insert(
+        &mut self,
+        mut node: Box<dyn NodeTrait<S, N>>,
+        events: &mut Vec<GraphEvent<S, N>>,
+    ) -> usize {
+        if !node.is_prepared() {
+            node.prepare(self.sample_rate, N);
+        }
+        node.take_graph_events(events);
+        let inputs = node.take_inputs();
+
+        let idx = self.nodes.len();
+        self.nodes.push(node);
+        self.inputs.push(vec![]);
+        self.bufs.push([S::zero_value(); N]);
+
+        for input in inputs {
+            let idx_input = self.insert(input, events);
+            self.inputs[idx].push(idx_input);
+        }
+
+        idx
+    }
+
+    // Whether `other` is the node `idx` or one of its (direct or not) inputs
+    //
+    // The search is iterative and visits every node once, in the storage of the compilation
+    fn depends_on(&mut self, idx: usize, other: usize) -> bool {
+        let Scratch { order, visited, .. } = &mut self.scratch;
+        visited.clear();
+        visited.resize(self.nodes.len(), false);
+        // Nodes to visit
+        let stack = order;
+        stack.clear();
+        stack.push(idx);
+
+        while let Some(idx) = stack.pop() {
+            if idx == other {
+                return true;
+            }
+            for input in self.inputs[idx].iter() {
+                if !visited[*input] {
+                    visited[*input] = true;
+                    stack.push(*input);
+                }
+            }
+        }
+
+        false
+    }
+
+    // Sort the nodes in topological order, grouped by level
+    //
+    // Nodes that cannot be reached from the root anymore are moved to `removed`
+    fn compile(&mut self) {
+        let num_nodes = self.nodes.len();
+        let mut scratch = std::mem::replace(&mut self.scratch, Scratch::new());
+        let Scratch {
+            order,
+            visited,
+            level,
+            new_idx,
+            ..
+        } = &mut scratch;
+
+        // 1. Depth first search from the root, a node is pushed after all its inputs
+        order.clear();
+        visited.clear();
+        visited.resize(num_nodes, false);
+        self.visit(self.root, visited, order);
+
+        // The inputs of a node are visited before it so that their level is already known
+        level.clear();
+        level.resize(num_nodes, 0);
+        new_idx.clear();
+        new_idx.resize(num_nodes, 0);
+        for (position, idx) in order.iter().enumerate() {
+            level[*idx] = self.inputs[*idx]
+                .iter()
+                .map(|input| level[*input] + 1)
+                .max()
+                .unwrap_or(0);
+            new_idx[*idx] = position;
+        }
+        // The nodes of a level stay in the order of the search. The sort does not
+        // allocate, unlike a stable sort
+        order.sort_unstable_by_key(|idx| (level[*idx], new_idx[*idx]));
+
+        // 2. Move the nodes, their inputs and their buffers at their new indices
+        for (idx, old_idx) in order.iter().enumerate() {
+            new_idx[*old_idx] = idx;
+        }
+
+        scratch.nodes.extend(self.nodes.drain(..).map(Some));
+        std::mem::swap(&mut self.inputs, &mut scratch.inputs);
+        std::mem::swap(&mut self.bufs, &mut scratch.bufs);
+
+        for old_idx in scratch.order.iter() {
+            self.nodes.push(scratch.nodes[*old_idx].take().unwrap());
+            let mut inputs = std::mem::take(&mut scratch.inputs[*old_idx]);
+            for input in inputs.iter_mut() {
+                *input = scratch.new_idx[*input];
+            }
+            self.inputs.push(inputs);
+            self.bufs.push(scratch.bufs[*old_idx]);
+        }
+        self.removed.extend(scratch.nodes.drain(..).flatten());
+        scratch.inputs.clear();
+        scratch.bufs.clear();
+
+        self.levels.clear();
+        for (idx, old_idx) in scratch.order.iter().enumerate() {
+            let level = &scratch.level;
+            match self.levels.last_mut() {
+                Some(range) if level[scratch.order[range.start]] == level[*old_idx] => {
+                    range.end = idx + 1
+                }
+                _ => self.levels.push(idx..(idx + 1)),
+            }
+        }
+
+        // 3. The root is the last node, it depends on all the other nodes
+        self.root = self.nodes.len() - 1;
+        self.names.clear();
+        for (idx, node) in self.nodes[..self.root].iter().enumerate() {
+            self.names.insert(node.get_name(), idx);
+        }
+
+        self.scratch = scratch;
+    }
+
+    fn visit(&self, idx: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+        if visited[idx] {
+            return;
+        }
+        visited[idx] = true;
+
+        for input in self.inputs[idx].iter() {
+            self.visit(*input, visited, order);
+        }
+
+        order.push(idx);
+    }
+}
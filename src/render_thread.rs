@@ -0,0 +1,221 @@
+use crate::graph::Audiograph;
+
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Counters shared between the render thread and the consumer of its samples
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    underruns: AtomicUsize,
+    full_waits: AtomicUsize,
+}
+
+impl RenderStats {
+    /// Number of times samples have been requested while the ring buffer did not hold enough of them.
+    /// The missing samples are replaced by silence
+    pub fn underruns(&self) -> usize {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the render thread waited for the consumer because the ring buffer was full.
+    /// This is the normal state of a stream rendered ahead of its consumer, not a failure
+    pub fn full_waits(&self) -> usize {
+        self.full_waits.load(Ordering::Relaxed)
+    }
+}
+
+/// An audio graph rendered on a dedicated thread
+///
+/// The blocks of N samples are pushed into a ring buffer as soon as there is room for
+/// them. The samples are pulled lock-free from the consumer side, typically from an
+/// audio callback. Dropping it stops the render thread.
+pub struct RenderThread<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    consumer: Consumer<S>,
+    stats: Arc<RenderStats>,
+    running: Arc<AtomicBool>,
+    sample_rate: u32,
+    thread: Option<JoinHandle<Audiograph<S, N>>>,
+}
+
+impl<S, const N: usize> RenderThread<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    pub(crate) fn spawn(mut audio: Audiograph<S, N>, capacity: usize) -> Self {
+        // A whole block has to fit into the ring buffer
+        let (producer, consumer) = RingBuffer::new(capacity.max(N));
+        let stats = Arc::new(RenderStats::default());
+        let running = Arc::new(AtomicBool::new(true));
+        let sample_rate = audio.get_sampling_rate().hz() as u32;
+
+        // Wait for about half a block when the ring buffer is full
+        let backoff = Duration::from_secs_f32(N as f32 / audio.get_sampling_rate().hz() / 2.0);
+
+        let thread = {
+            let stats = stats.clone();
+            let running = running.clone();
+            std::thread::spawn(move || {
+                render_loop(&mut audio, producer, &stats, &running, backoff);
+                audio
+            })
+        };
+
+        Self {
+            consumer,
+            stats,
+            running,
+            sample_rate,
+            thread: Some(thread),
+        }
+    }
+
+    /// Pop the next sample, or silence if it has not been rendered yet
+    pub fn pop(&mut self) -> S {
+        match self.consumer.pop() {
+            Ok(sample) => sample,
+            Err(_) => {
+                self.stats.underruns.fetch_add(1, Ordering::Relaxed);
+                S::zero_value()
+            }
+        }
+    }
+
+    /// Fill a buffer with the next samples
+    ///
+    /// The samples that have not been rendered yet are replaced by silence
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to fill, usually the one given by the audio callback
+    ///
+    /// # Return
+    ///
+    /// * the number of rendered samples written into the buffer
+    pub fn read_into(&mut self, buf: &mut [S]) -> usize {
+        let num_samples = self.consumer.slots().min(buf.len());
+        if let Ok(chunk) = self.consumer.read_chunk(num_samples) {
+            let (first, second) = chunk.as_slices();
+            buf[..first.len()].copy_from_slice(first);
+            buf[first.len()..num_samples].copy_from_slice(second);
+            chunk.commit_all();
+        }
+
+        if num_samples < buf.len() {
+            self.stats.underruns.fetch_add(1, Ordering::Relaxed);
+            for sample in buf[num_samples..].iter_mut() {
+                *sample = S::zero_value();
+            }
+        }
+
+        num_samples
+    }
+
+    /// The consumer side of the ring buffer the graph is rendered into
+    ///
+    /// Samples popped directly from it are not accounted in the underruns
+    pub fn consumer(&mut self) -> &mut Consumer<S> {
+        &mut self.consumer
+    }
+
+    /// The counters of the underruns and of the waits of the render thread, they can be read from any thread
+    pub fn stats(&self) -> Arc<RenderStats> {
+        self.stats.clone()
+    }
+
+    /// Stop the render thread and give the graph back
+    ///
+    /// The samples remaining in the ring buffer are dropped
+    ///
+    /// # Panics
+    ///
+    /// If the render thread panicked, the panic is raised again
+    pub fn stop(mut self) -> Audiograph<S, N> {
+        match self.join() {
+            Some(Ok(audio)) => audio,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => unreachable!("the render thread is only joined once"),
+        }
+    }
+
+    fn join(&mut self) -> Option<std::thread::Result<Audiograph<S, N>>> {
+        self.running.store(false, Ordering::Release);
+        self.thread.take().map(|thread| thread.join())
+    }
+}
+
+fn render_loop<S, const N: usize>(
+    audio: &mut Audiograph<S, N>,
+    mut producer: Producer<S>,
+    stats: &RenderStats,
+    running: &AtomicBool,
+    backoff: Duration,
+) where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    while running.load(Ordering::Acquire) {
+        // Wait for the consumer to make room before rendering, so that every rendered
+        // block is pushed and the clock of the graph follows the samples pushed
+        if producer.slots() < N {
+            stats.full_waits.fetch_add(1, Ordering::Relaxed);
+            while producer.slots() < N {
+                if !running.load(Ordering::Acquire) || producer.is_abandoned() {
+                    return;
+                }
+                std::thread::sleep(backoff);
+            }
+        }
+
+        audio.stream_into_rtrb(true);
+        let chunk = producer.write_chunk_uninit(N).unwrap();
+        chunk.fill_from_iter(audio.get_buf().iter().copied());
+    }
+}
+
+impl<S, const N: usize> Drop for RenderThread<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        // A panic of the render thread has already been reported, it is not raised again here
+        let _ = self.join();
+    }
+}
+
+impl<S, const N: usize> Iterator for RenderThread<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    type Item = S;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.pop())
+    }
+}
+
+// The render thread can be played directly by a rodio sink
+impl<S, const N: usize> rodio::Source for RenderThread<S, N>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
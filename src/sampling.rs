@@ -1,10 +1,15 @@
-#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd)]
 pub struct SampleIdx(pub usize);
 
 #[derive(Clone, Copy)]
 pub struct SamplingRate(f32);
 impl SamplingRate {
-    pub(crate) fn from_time(&self, dur: std::time::Duration) -> SampleIdx {
+    // Number of samples per second
+    pub(crate) fn hz(&self) -> f32 {
+        self.0
+    }
+
+    pub(crate) fn sample_idx_at(&self, dur: std::time::Duration) -> SampleIdx {
         SampleIdx((self.0 * dur.as_secs_f32()) as usize)
     }
 }
@@ -14,3 +19,14 @@ impl From<f32> for SamplingRate {
         SamplingRate(a)
     }
 }
+
+/// The timeline of a graph, used to schedule the events at a sample index
+pub trait Timeline<S, const N: usize>
+where
+    S: rodio::Sample + Send + Sync + 'static,
+{
+    /// The absolute sample index occuring at `time` from the start of the graph
+    fn sample_idx_at(&self, time: std::time::Duration) -> SampleIdx;
+    /// Number of samples per second
+    fn sample_rate(&self) -> f32;
+}